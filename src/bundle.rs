@@ -0,0 +1,114 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::installer::{self, InstallStep, LoaderVersion, MinecraftVersion, ProgressCallback};
+
+// A bundle is a directory holding everything an install would otherwise download:
+// the launch jsons, the vanilla server jar and the libraries laid out like a maven repository,
+// next to a manifest naming the versions
+const MANIFEST: &str = "manifest.json";
+pub const PROFILE_JSON: &str = "profile.json";
+pub const SERVER_JSON: &str = "server.json";
+pub const SERVER_JAR: &str = "server.jar";
+const LIBRARIES: &str = "libraries";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Manifest {
+    pub minecraft_version: MinecraftVersion,
+    pub loader_version: LoaderVersion,
+}
+
+/// Downloads everything needed to install the client or server into a bundle at `dir`
+pub async fn create(
+    client: Client,
+    meta_urls: &[String],
+    dir: &Path,
+    minecraft_version: MinecraftVersion,
+    loader_version: LoaderVersion,
+    mojang_meta_url: &str,
+    progress: ProgressCallback<'_>,
+) -> Result<()> {
+    println!(
+        "Bundling Quilt Loader {loader_version} for Minecraft {minecraft_version} to {}",
+        dir.display()
+    );
+    fs::create_dir_all(dir)?;
+
+    let profile_json = installer::download_profile_json(
+        &client,
+        meta_urls,
+        &minecraft_version,
+        &loader_version,
+        progress,
+    )
+    .await?;
+    let server_json =
+        installer::download_server_json(&client, meta_urls, &minecraft_version, &loader_version)
+            .await?;
+
+    println!("Downloading libraries...");
+    let mut libraries = installer::library_downloads(&profile_json)?;
+    libraries.extend(installer::library_downloads(&server_json)?);
+    for (url, path) in libraries {
+        let destination = dir.join(LIBRARIES).join(&path);
+        // The client and server share most libraries
+        if !destination.exists() {
+            installer::download_file(
+                &client,
+                &url,
+                &destination,
+                InstallStep::DownloadLibraries,
+                None,
+            )
+            .await?;
+        }
+    }
+
+    let url =
+        installer::fetch_vanilla_server_url(&client, mojang_meta_url, &minecraft_version).await?;
+    installer::download_file(
+        &client,
+        &url,
+        &dir.join(SERVER_JAR),
+        InstallStep::DownloadServerJar,
+        progress,
+    )
+    .await?;
+
+    fs::write(dir.join(PROFILE_JSON), profile_json)?;
+    fs::write(dir.join(SERVER_JSON), server_json)?;
+    // Written last, so an interrupted download doesn't leave something that looks like a bundle
+    fs::write(
+        dir.join(MANIFEST),
+        serde_json::to_string_pretty(&Manifest {
+            minecraft_version,
+            loader_version,
+        })?,
+    )?;
+
+    println!("Bundle created successfully.");
+    Ok(())
+}
+
+pub fn read_manifest(dir: &Path) -> Result<Manifest> {
+    Ok(serde_json::from_str(&read(dir, MANIFEST)?)?)
+}
+
+/// Reads one of the files in a bundle
+pub fn read(dir: &Path, file: &str) -> Result<String> {
+    fs::read_to_string(dir.join(file))
+        .with_context(|| format!("{} is not a valid bundle, {file} is missing", dir.display()))
+}
+
+/// Copies a library from the bundle, given its maven path
+pub fn copy_library(dir: &Path, path: &str, destination: &Path) -> Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(dir.join(LIBRARIES).join(path), destination)
+        .with_context(|| format!("The bundle is missing the library {path}"))?;
+    Ok(())
+}
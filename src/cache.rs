@@ -0,0 +1,123 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+
+#[cfg(target_os = "windows")]
+fn get_default_cache_directory() -> PathBuf {
+    PathBuf::from(std::env::var("LOCALAPPDATA").unwrap())
+        .join("quilt-installer")
+        .join("cache")
+}
+
+#[cfg(target_os = "macos")]
+fn get_default_cache_directory() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap())
+        .join("Library")
+        .join("Caches")
+        .join("quilt-installer")
+}
+
+#[cfg(target_os = "linux")]
+fn get_default_cache_directory() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(std::env::var("HOME").unwrap()).join(".cache"))
+        .join("quilt-installer")
+}
+
+/// The cache directory, `QUILT_INSTALLER_CACHE_DIR` if set, for read-only home directories
+/// and other setups where the platform default doesn't work
+pub fn get_cache_directory() -> PathBuf {
+    std::env::var_os("QUILT_INSTALLER_CACHE_DIR")
+        .filter(|dir| !dir.is_empty())
+        .map_or_else(get_default_cache_directory, PathBuf::from)
+}
+
+/// Reads a previously cached meta response
+pub fn read(key: &str) -> Option<String> {
+    fs::read_to_string(get_cache_directory().join(key)).ok()
+}
+
+static WRITES_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Stops meta responses from being cached from now on, for commands that mustn't write anything
+pub fn disable_writes() {
+    WRITES_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Caches a meta response for offline use. Caching is best-effort, so failures are only logged
+pub fn write(key: &str, contents: &str) {
+    if WRITES_DISABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let path = get_cache_directory().join(key);
+    let result = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, contents));
+    if let Err(error) = result {
+        eprintln!("Failed to cache {}: {error}", path.display());
+    }
+}
+
+/// The part of the cache holding the artifacts installs link their libraries from
+const ARTIFACTS_DIR: &str = "artifacts";
+
+/// Where the shared cache keeps the artifact with the SHA-1 hash, named after the hash
+/// so installs of different versions share the libraries they have in common
+pub fn artifact_path(sha1: &str) -> PathBuf {
+    get_cache_directory()
+        .join(ARTIFACTS_DIR)
+        .join(&sha1[..2])
+        .join(sha1)
+}
+
+/// Puts a cached artifact at `destination`, as a hard link to save space,
+/// or as a copy where links aren't possible, like across drives
+pub fn link_artifact(artifact: &Path, destination: &Path) -> Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if destination.exists() {
+        fs::remove_file(destination)?;
+    }
+    if fs::hard_link(artifact, destination).is_err() {
+        fs::copy(artifact, destination)?;
+    }
+    Ok(())
+}
+
+/// Deletes the cached meta responses, and the shared artifacts too if `artifacts` is set.
+/// Installs linked to the artifacts keep working without them, but the next install
+/// downloads them all again
+pub fn clear(artifacts: bool) -> Result<()> {
+    let dir = get_cache_directory();
+    if artifacts && dir.exists() {
+        fs::remove_dir_all(&dir)?;
+    } else if dir.exists() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_name() == ARTIFACTS_DIR {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(entry.path())?;
+            } else {
+                fs::remove_file(entry.path())?;
+            }
+        }
+    }
+    if artifacts {
+        println!("Cleared the cache at {}", dir.display());
+    } else {
+        println!(
+            "Cleared the cache at {}, except for the shared artifacts",
+            dir.display()
+        );
+    }
+    Ok(())
+}
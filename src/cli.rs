@@ -1,12 +1,26 @@
 use crate::installer::{
-    self, ClientInstallation, LoaderVersion, MinecraftVersion, ServerInstallation,
+    self, ClientInstallation, InstallProgress, InstallStep, JsonStyle, LoaderVersion, MavenLoader,
+    MinecraftVersion, OnConflict, ProfileType, ServerInstallation,
 };
+use crate::{
+    bundle, cache, launcher,
+    lockfile::{self, LockedJson},
+    settings,
+};
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use derive_more::Display;
 use reqwest::Client;
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinHandle;
 
 #[derive(Parser)]
 #[command(about, version, propagate_version = true)]
@@ -18,9 +32,15 @@ pub struct Args {
     /// Pick between the
     /// latest `stable` version,
     /// latest `snapshot`,
+    /// latest `recommended` version, the same as `stable` until Quilt meta recommends versions,
     /// or a specific version number.
+    /// Servers can be installed for a comma separated list of version numbers,
+    /// each into a subdirectory named after the version.
     #[arg(short = 'm', long, default_value_t)]
     minecraft_version: MCVersionCLI,
+    /// Read the Minecraft version number to install from a file, instead of `--minecraft-version`
+    #[arg(long, value_name = "FILE", conflicts_with = "minecraft_version")]
+    minecraft_version_file: Option<PathBuf>,
     /// The Quilt loader version to install
     ///
     /// Pick between the
@@ -29,6 +49,155 @@ pub struct Args {
     /// or a specific version number.
     #[arg(short = 'l', long, default_value_t)]
     loader_version: LoaderVersionCLI,
+    /// Fail instead of installing if the Minecraft version resolves to a snapshot
+    #[arg(long, visible_alias = "no-snapshots")]
+    stable_only: bool,
+    /// Install exactly the versions pinned in the lockfile, failing if meta no longer serves
+    /// the same launch jsons for them
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "minecraft_version",
+            "minecraft_version_file",
+            "loader_version",
+            "update_lock",
+        ]
+    )]
+    locked: bool,
+    /// Pin the versions the install resolves to in the lockfile, for installing them again
+    /// with `--locked`
+    #[arg(long)]
+    update_lock: bool,
+    /// The lockfile `--locked` reads and `--update-lock` writes
+    #[arg(long, value_name = "FILE", default_value = lockfile::DEFAULT_PATH)]
+    lockfile: PathBuf,
+    /// Print more details about what was installed
+    #[arg(short = 'v', long, global = true)]
+    verbose: bool,
+    /// Only print a single line once installed, like `installed <ID> -> <INSTALL_DIR>`.
+    /// Progress and other details are left out
+    #[arg(long, global = true, conflicts_with = "verbose")]
+    brief: bool,
+    /// The base URL of Quilt meta. Repeat it to list mirrors, which are tried in order
+    /// when one of them fails
+    #[arg(
+        long = "meta-url",
+        value_name = "URL",
+        global = true,
+        env = "QUILT_INSTALLER_META_URLS",
+        value_delimiter = ','
+    )]
+    meta_url: Vec<String>,
+    /// Only connect over IPv4
+    ///
+    /// Hostnames are resolved by the system resolver.
+    /// By default both address families are tried.
+    #[arg(short = '4', long, global = true, conflicts_with = "ipv6")]
+    pub ipv4: bool,
+    /// Only connect over IPv6
+    #[arg(short = '6', long, global = true)]
+    pub ipv6: bool,
+    /// How many seconds to wait for a connection to be established
+    #[arg(long, value_name = "SECONDS", global = true, default_value_t = 10)]
+    pub connect_timeout: u64,
+    /// How many seconds a whole request, including its download, may take. Unlimited by default
+    #[arg(long, value_name = "SECONDS", global = true)]
+    pub timeout: Option<u64>,
+    /// How many seconds unused connections are kept open for reuse
+    #[arg(long, value_name = "SECONDS", global = true, default_value_t = 90)]
+    pub pool_idle_timeout: u64,
+    /// The most unused connections kept open per host. Unlimited by default
+    #[arg(long, value_name = "COUNT", global = true)]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Limit each download to this many bytes per second, to leave bandwidth for others
+    /// on a shared connection. Unlimited by default
+    #[arg(
+        long,
+        value_name = "BYTES_PER_SECOND",
+        global = true,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub max_rate: Option<u64>,
+    /// How many times a request that failed from a dropped connection, a timeout
+    /// or a server error is retried, waiting longer before each. 0 fails at once
+    #[arg(long, value_name = "COUNT", global = true, default_value_t = installer::DEFAULT_RETRIES)]
+    pub retries: u32,
+    /// The User-Agent header sent with every request, for proxies or mirrors that require
+    /// a specific one. Defaults to `quilt-installer/<VERSION>`
+    #[arg(long, global = true, env = "QUILT_INSTALLER_USER_AGENT")]
+    user_agent: Option<String>,
+    /// Log the URL, status and size of every request to stderr,
+    /// to see which mirror or file was actually fetched
+    #[arg(long, global = true)]
+    pub trace_requests: bool,
+    /// Scale the GUI window and its contents by this factor, for HiDPI displays
+    #[arg(long, env = "QUILT_INSTALLER_SCALE", default_value_t = 1.0)]
+    pub scale: f64,
+    /// Interactively pick what to install in the terminal
+    #[arg(long)]
+    pub tui: bool,
+    /// Print where the client would be installed to and exit without installing.
+    /// Nothing is written, so it can't be combined with `--update-lock`
+    #[arg(long, global = true, conflicts_with = "update_lock")]
+    print_paths: bool,
+    /// Print the version and SHA-256 hash of this installer's executable, to compare against
+    /// the published checksums. Printed after the install succeeds when used with a subcommand
+    #[arg(long, global = true)]
+    pub self_hash: bool,
+    /// How install progress is reported
+    #[arg(long, value_enum, global = true, default_value_t)]
+    progress: ProgressFormat,
+    /// POST each progress event, and whether the install succeeded, as JSON to this URL,
+    /// for dashboards following automated installs. Failing to reach it doesn't fail the install
+    #[arg(long, value_name = "URL", global = true)]
+    progress_webhook: Option<String>,
+    /// Where each value given came from, for `config show`
+    #[arg(skip)]
+    value_sources: HashMap<String, ValueSource>,
+}
+
+impl Args {
+    /// Parses the command line, remembering where each value came from,
+    /// as the derived parser forgets it
+    pub fn parse_with_sources() -> Self {
+        let matches = Args::command().get_matches();
+        let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+        args.value_sources = matches
+            .ids()
+            .filter_map(|id| Some((id.to_string(), matches.value_source(id.as_str())?)))
+            .collect();
+        args
+    }
+
+    /// The Quilt meta mirrors to try in order, from the command line, the settings file,
+    /// or the default
+    pub fn meta_urls(&self) -> Vec<String> {
+        if !self.meta_url.is_empty() {
+            return self.meta_url.clone();
+        }
+        let settings = settings::load();
+        if !settings.meta_urls.is_empty() {
+            return settings.meta_urls;
+        }
+        vec![installer::QUILT_META_URL.into()]
+    }
+
+    pub fn user_agent(&self) -> String {
+        self.user_agent.clone().unwrap_or_else(|| {
+            concat!(env!("CARGO_PKG_NAME"), '/', env!("CARGO_PKG_VERSION")).into()
+        })
+    }
+
+    /// The local address to bind connections to, restricting them to one address family
+    pub fn local_address(&self) -> Option<IpAddr> {
+        if self.ipv4 {
+            Some(Ipv4Addr::UNSPECIFIED.into())
+        } else if self.ipv6 {
+            Some(Ipv6Addr::UNSPECIFIED.into())
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -38,13 +207,107 @@ pub enum Subcommands {
         /// Don't create a profile
         #[arg(short = 'P', long)]
         no_profile: bool,
+        /// The type of the created launcher profile
+        #[arg(long, value_enum, default_value_t)]
+        profile_type: ProfileType,
         /// The directory to install to
+        ///
+        /// `@launcher` stands for the detected launcher directory,
+        /// and `@launcher/<DIR>` for a directory inside it.
         #[arg(
             short = 'o',
             long,
-            default_value_os_t = installer::get_default_client_directory()
+            default_value_os_t = installer::detect_client_directory(),
+            value_parser = parse_client_dir
         )]
         install_dir: PathBuf,
+        /// The directory version profiles are installed to, if not `<INSTALL_DIR>/versions`
+        #[arg(long)]
+        versions_dir: Option<PathBuf>,
+        /// The launcher_profiles.json to add the profile to, if not the one in `<INSTALL_DIR>`.
+        /// For launchers that keep it somewhere else, the version files still go to
+        /// `<INSTALL_DIR>` or `--versions-dir`
+        #[arg(long, value_name = "FILE", conflicts_with = "no_profile")]
+        profiles_file: Option<PathBuf>,
+        /// Appended to the version id and profile name, like `quilt-loader-0.21.0-1.20.4-test`,
+        /// so another install of the same versions can sit next to this one
+        #[arg(long, value_parser = parse_id_suffix)]
+        id_suffix: Option<String>,
+        /// When reinstalling, only remove the files the installer created in the version directory
+        /// instead of the whole directory, keeping files added to it by hand
+        #[arg(long)]
+        keep_unknown_files: bool,
+        /// Write an empty `<ID>.jar` next to the version json, for launchers that won't start
+        /// a version without a jar of its own. The vanilla launcher doesn't need it
+        #[arg(long)]
+        placeholder_jar: bool,
+        /// The game directory for the launcher profile, to keep its mods, worlds and settings
+        /// apart from other profiles. The launcher's default is used if not set
+        #[arg(long, value_name = "DIR", conflicts_with = "no_profile")]
+        game_dir: Option<PathBuf>,
+        /// Install even if Quilt doesn't support the Minecraft version yet, adapting the profile
+        /// of the newest supported version. Meant for testing new snapshots, it may not launch
+        #[arg(long, conflicts_with_all = ["offline", "bundle"])]
+        allow_unsupported: bool,
+        /// Leave libraries whose maven coordinates start with this, like `org.ow2.asm:asm`,
+        /// out of the launch json. Repeat it to exclude several. Meant for debugging launch issues
+        #[arg(long = "exclude-library", value_name = "MAVEN_PREFIX")]
+        exclude_libraries: Vec<String>,
+        /// Install a loader build meta doesn't list, like `org.quiltmc:quilt-loader:0.22.0-local`,
+        /// from `--loader-maven-repo`. The profile of `--loader-version` is adapted to it.
+        /// Meant for Quilt developers testing unreleased builds
+        #[arg(
+            long,
+            value_name = "GROUP:ARTIFACT:VERSION",
+            value_parser = parse_loader_maven,
+            conflicts_with_all = ["offline", "bundle", "allow_unsupported"]
+        )]
+        loader_maven: Option<LoaderVersion>,
+        /// The maven repository the `--loader-maven` build is published to
+        #[arg(
+            long,
+            value_name = "URL",
+            requires = "loader_maven",
+            default_value = installer::QUILT_MAVEN_URL
+        )]
+        loader_maven_repo: String,
+        /// The URL of the Mojang version manifest the Minecraft version's release date is
+        /// looked up in, to warn about loader builds that predate it
+        #[arg(
+            long,
+            env = "QUILT_INSTALLER_MOJANG_META_URL",
+            default_value = installer::MOJANG_VERSION_MANIFEST_URL
+        )]
+        mojang_meta_url: String,
+        /// Install without network access, using the versions and profile cached by a previous run
+        #[arg(long)]
+        offline: bool,
+        /// Write launcher_profiles.json with sorted keys, for reproducible output
+        #[arg(long)]
+        canonical: bool,
+        /// Keep the creation time of a replaced launcher profile instead of using the current time,
+        /// so repeated installs produce identical output
+        #[arg(long)]
+        deterministic: bool,
+        /// Write the launch json indented for reading, instead of exactly as meta sent it
+        #[arg(long, conflicts_with = "compact")]
+        pretty: bool,
+        /// Write the launch json without whitespace, instead of exactly as meta sent it
+        #[arg(long)]
+        compact: bool,
+        /// Install from a bundle created by the `bundle` subcommand, without network access.
+        /// The versions are the ones in the bundle
+        #[arg(long, value_name = "DIR", conflicts_with = "offline")]
+        bundle: Option<PathBuf>,
+        /// Only add the launcher profile back, for version files that are already installed
+        #[arg(long, conflicts_with_all = ["no_profile", "bundle"])]
+        refresh_profiles_only: bool,
+        /// Start the Minecraft launcher once the client is installed
+        #[arg(long)]
+        launch: bool,
+        /// What to do if this version is already installed
+        #[arg(long, value_enum, default_value_t)]
+        on_conflict: OnConflict,
     },
     /// Install the Quilt standalone server
     Server {
@@ -54,20 +317,261 @@ pub enum Subcommands {
         /// Do not download the server jar
         #[arg(short = 'J', long)]
         no_jar: bool,
+        /// The Java executable to reference in the launch scripts
+        #[arg(long)]
+        java: Option<PathBuf>,
+        /// The file name of the launch jar the scripts start
+        #[arg(long, value_name = "FILE_NAME")]
+        launch_jar_name: Option<String>,
         /// The directory to install to
         #[arg(short = 'o', long)]
         install_dir: PathBuf,
+        /// The directory to download the server artifacts to, if not the install directory
+        #[arg(long)]
+        output_dir: Option<PathBuf>,
+        /// The URL of the Mojang version manifest the vanilla server jar is looked up in
+        #[arg(
+            long,
+            env = "QUILT_INSTALLER_MOJANG_META_URL",
+            default_value = installer::MOJANG_VERSION_MANIFEST_URL
+        )]
+        mojang_meta_url: String,
+        /// Also generate a systemd service running the launch script (Linux only)
+        #[arg(long)]
+        systemd: bool,
+        /// The account the systemd service runs the server as. Without it, the service
+        /// has a placeholder to fill in before it will start
+        #[arg(long, value_name = "USER", requires = "systemd")]
+        systemd_user: Option<String>,
+        /// Generate the launch scripts for every platform, `start.bat`, `start.sh` and macOS's
+        /// `start.command`, instead of only the current one's, for servers that will be run
+        /// on another OS
+        #[arg(long, conflicts_with = "no_launch_script")]
+        all_scripts: bool,
+        /// Download the vanilla server jar again even if the one in the directory
+        /// already matches Mojang's hash for the Minecraft version
+        #[arg(long, conflicts_with = "no_jar")]
+        force: bool,
+        /// Keep the downloaded libraries in a cache shared by all installs, and link them
+        /// into the server from there, so installing more servers doesn't download them again
+        #[arg(long, conflicts_with = "bundle")]
+        shared_cache: bool,
+        /// Install from a bundle created by the `bundle` subcommand, without network access.
+        /// The versions are the ones in the bundle
+        #[arg(long, value_name = "DIR")]
+        bundle: Option<PathBuf>,
+        /// Write a readme explaining how to run the server.
+        /// By default, it's only written into directories that were empty
+        #[arg(long, conflicts_with = "no_readme")]
+        readme: bool,
+        /// Never write the readme
+        #[arg(long)]
+        no_readme: bool,
+    },
+    /// Download everything needed to install the client or server offline into a bundle directory
+    Bundle {
+        /// The directory to create the bundle in
+        #[arg(short = 'o', long)]
+        output_dir: PathBuf,
+        /// The URL of the Mojang version manifest the vanilla server jar is looked up in
+        #[arg(
+            long,
+            env = "QUILT_INSTALLER_MOJANG_META_URL",
+            default_value = installer::MOJANG_VERSION_MANIFEST_URL
+        )]
+        mojang_meta_url: String,
     },
+    /// Check that meta is reachable, the versions resolve and the install directory is valid,
+    /// without installing anything
+    Check {
+        /// Check the directory for a server install instead of a client install
+        #[arg(long)]
+        server: bool,
+        /// The directory that would be installed to, the detected client directory by default.
+        /// `@launcher` expands like it does for the client subcommand
+        #[arg(short = 'o', long, value_parser = parse_client_dir)]
+        install_dir: Option<PathBuf>,
+    },
+    /// Print the Minecraft and loader versions `--minecraft-version` and `--loader-version`
+    /// resolve to, like `1.20.4 0.21.0`, to pin them in later installs
+    Resolve {
+        /// How to print the versions
+        #[arg(long, value_enum, default_value_t)]
+        format: ResolveFormat,
+    },
+    /// Check that installed Quilt client profiles are intact
+    Verify {
+        /// The client directory to check.
+        /// `@launcher` expands like it does for the client subcommand
+        #[arg(
+            short = 'o',
+            long,
+            default_value_os_t = installer::detect_client_directory(),
+            value_parser = parse_client_dir
+        )]
+        install_dir: PathBuf,
+        /// The directory version profiles are installed to, if not `<INSTALL_DIR>/versions`
+        #[arg(long)]
+        versions_dir: Option<PathBuf>,
+        /// The version id of the profile to check, like `quilt-loader-0.21.0-1.20.1`.
+        /// All Quilt profiles are checked by default
+        #[arg(long)]
+        profile: Option<String>,
+    },
+    /// Remove installed Quilt client versions and the launcher profiles using them.
+    /// All of them are matched unless filtered, and removing more than one is confirmed first
+    Uninstall {
+        /// The client directory to uninstall from.
+        /// `@launcher` expands like it does for the client subcommand
+        #[arg(
+            short = 'o',
+            long,
+            default_value_os_t = installer::detect_client_directory(),
+            value_parser = parse_client_dir
+        )]
+        install_dir: PathBuf,
+        /// The directory version profiles are installed to, if not `<INSTALL_DIR>/versions`
+        #[arg(long)]
+        versions_dir: Option<PathBuf>,
+        /// The launcher_profiles.json to remove the profiles from,
+        /// if not the one in the install directory
+        #[arg(long, value_name = "FILE")]
+        profiles_file: Option<PathBuf>,
+        /// Only remove the version with this id, like `quilt-loader-0.21.0-1.20.1`
+        #[arg(long, conflicts_with_all = ["for_minecraft", "loader"])]
+        profile: Option<String>,
+        /// Only remove the versions for this Minecraft version
+        #[arg(long = "for", value_name = "MINECRAFT_VERSION")]
+        for_minecraft: Option<String>,
+        /// Only remove the versions using this Quilt Loader version
+        #[arg(long, value_name = "LOADER_VERSION")]
+        loader: Option<String>,
+        /// Remove several versions without asking first
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+    /// Check that a launcher_profiles.json can be read, and list the Quilt profiles in it,
+    /// without changing it
+    ValidateProfiles {
+        /// The launcher_profiles.json to check
+        path: PathBuf,
+    },
+    /// List the versions available on meta, newest first
+    List {
+        #[command(subcommand)]
+        versions: ListVersions,
+        /// How to print the versions
+        #[arg(long, value_enum, default_value_t, global = true)]
+        format: ListFormat,
+    },
+    /// Print what meta knows about a version
+    Describe {
+        #[command(subcommand)]
+        version: DescribeVersion,
+    },
+    /// Inspect the installer's configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Manage the cache of downloaded version lists and profiles
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ListVersions {
+    /// List the Minecraft versions
+    Minecraft,
+    /// List the Quilt Loader versions
+    Loader {
+        /// Only list the versions that can be installed for this Minecraft version
+        #[arg(long = "for", value_name = "MINECRAFT_VERSION")]
+        for_minecraft: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum ProgressFormat {
+    /// The name of each step on stdout as it starts
+    #[default]
+    Text,
+    /// One JSON object per progress event on stderr,
+    /// like `{"stage":"download-libraries","fraction":0.42}`, keeping stdout for the result
+    Json,
+}
+
+impl ProgressFormat {
+    fn reporter(self) -> fn(InstallProgress) {
+        match self {
+            ProgressFormat::Text => print_progress,
+            ProgressFormat::Json => print_json_progress,
+        }
+    }
 }
-#[derive(Clone, PartialEq, Eq, Default, Display)]
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum ResolveFormat {
+    /// The Minecraft and loader versions separated by a space
+    #[default]
+    Plain,
+    /// An object with `minecraft_version` and `loader_version` fields
+    Json,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+pub enum ListFormat {
+    /// One version per line
+    #[default]
+    Plain,
+    /// An array of objects with `version` and `stable` fields
+    Json,
+    /// `version,stable` rows below a header
+    Csv,
+}
+
+#[derive(Subcommand)]
+pub enum DescribeVersion {
+    /// Describe a Quilt Loader version
+    Loader { version: String },
+    /// Describe a Minecraft version
+    Minecraft { version: String },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the settings an install would use and where each of them comes from
+    Show,
+}
+
+#[derive(Subcommand)]
+pub enum CacheAction {
+    /// Print the location of the cache
+    Path,
+    /// Delete the cached meta responses. The shared artifacts `--shared-cache` installs link
+    /// their libraries from are kept unless `--artifacts` is given
+    Clear {
+        /// Delete the shared artifacts as well. Installed servers keep their libraries,
+        /// but installing again downloads them all again
+        #[arg(long)]
+        artifacts: bool,
+    },
+}
+// Serialized as the same strings accepted on the command line
+#[derive(Clone, PartialEq, Eq, Default, Display, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum MCVersionCLI {
     #[default]
     Stable,
     Snapshot,
+    Recommended,
     Custom(String),
 }
 
-#[derive(Clone, PartialEq, Eq, Default, Display)]
+#[derive(Clone, PartialEq, Eq, Default, Display, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum LoaderVersionCLI {
     #[default]
     Stable,
@@ -80,6 +584,7 @@ impl From<String> for MCVersionCLI {
         match s.to_lowercase().as_ref() {
             "stable" => Self::Stable,
             "snapshot" => Self::Snapshot,
+            "recommended" => Self::Recommended,
             _ => Self::Custom(s),
         }
     }
@@ -95,63 +600,1038 @@ impl From<String> for LoaderVersionCLI {
     }
 }
 
+impl From<MCVersionCLI> for String {
+    fn from(version: MCVersionCLI) -> Self {
+        version.to_string()
+    }
+}
+
+impl From<LoaderVersionCLI> for String {
+    fn from(version: LoaderVersionCLI) -> Self {
+        version.to_string()
+    }
+}
+
 pub async fn cli(client: Client, args: Args) -> Result<()> {
-    let (minecraft_version, loader_version) =
-        get_versions(client.clone(), args.minecraft_version, args.loader_version).await?;
+    let Some(url) = args.progress_webhook.clone() else {
+        return run(client, args, None).await;
+    };
+    let webhook = Webhook::start(client.clone(), url);
+    let result = run(client, args, Some(&webhook)).await;
+    webhook.finish(&result).await;
+    result
+}
+
+async fn run(client: Client, args: Args, webhook: Option<&Webhook>) -> Result<()> {
+    if let Some(Subcommands::Config {
+        action: ConfigAction::Show,
+    }) = &args.subcommand
+    {
+        return show_config(&args);
+    }
+
+    if args.print_paths && !matches!(args.subcommand, Some(Subcommands::Client { .. })) {
+        bail!("--print-paths only works with the client subcommand");
+    }
+
+    let meta_urls = args.meta_urls();
+    let print: fn(InstallProgress) = match args.progress {
+        ProgressFormat::Text if args.brief => |_| (),
+        progress => progress.reporter(),
+    };
+    let webhook = webhook.map(|webhook| webhook.events.clone());
+    let report = move |progress: InstallProgress| {
+        print(progress);
+        if let Some(webhook) = &webhook {
+            let _ = webhook.send(progress_json(progress));
+        }
+    };
+    let minecraft_version = match &args.minecraft_version_file {
+        Some(path) => read_minecraft_version_file(path)?,
+        None => args.minecraft_version,
+    };
 
     match args.subcommand.unwrap() {
         Subcommands::Client {
             no_profile,
+            profile_type,
             install_dir,
+            versions_dir,
+            profiles_file,
+            id_suffix,
+            keep_unknown_files,
+            placeholder_jar,
+            game_dir,
+            allow_unsupported,
+            exclude_libraries,
+            loader_maven,
+            loader_maven_repo,
+            mojang_meta_url,
+            offline,
+            canonical,
+            deterministic,
+            pretty,
+            compact,
+            bundle,
+            refresh_profiles_only,
+            launch,
+            on_conflict,
         } => {
-            installer::install_client(
-                client,
-                ClientInstallation {
-                    minecraft_version,
-                    loader_version,
-                    install_dir,
-                    generate_profile: !no_profile,
+            if args.locked && (offline || bundle.is_some()) {
+                bail!("--locked checks the lockfile against meta, so it needs network access");
+            }
+            let ((minecraft_version, loader_version), locked_json) = match &bundle {
+                Some(bundle) => (bundle_versions(bundle)?, None),
+                None if args.locked => {
+                    let locked = lockfile::read(&args.lockfile)?;
+                    let locked_json = LockedJson {
+                        sha256: locked.profile_json_sha256,
+                        path: args.lockfile.clone(),
+                    };
+                    (
+                        (locked.minecraft_version, locked.loader_version),
+                        Some(locked_json),
+                    )
+                }
+                None => {
+                    let versions = get_versions(
+                        client.clone(),
+                        &meta_urls,
+                        minecraft_version,
+                        args.loader_version,
+                        args.stable_only,
+                        offline,
+                        allow_unsupported,
+                    )
+                    .await?;
+                    if args.update_lock {
+                        update_lockfile(&client, &meta_urls, &args.lockfile, &versions, args.brief)
+                            .await?;
+                    }
+                    (versions, None)
+                }
+            };
+            // The version resolved from meta is the one whose profile the build's is adapted from
+            let (loader_version, maven_loader) = match loader_maven {
+                Some(build) => (
+                    build,
+                    Some(MavenLoader {
+                        repository: loader_maven_repo,
+                        base_version: loader_version,
+                    }),
+                ),
+                None => (loader_version, None),
+            };
+            let installation = ClientInstallation {
+                minecraft_version,
+                loader_version,
+                install_dir,
+                versions_dir,
+                profiles_file,
+                id_suffix,
+                keep_unknown_files,
+                placeholder_jar,
+                game_dir,
+                allow_unsupported,
+                exclude_libraries,
+                maven_loader,
+                on_conflict,
+                generate_profile: !no_profile,
+                profile_type,
+                offline,
+                canonical,
+                deterministic,
+                json_style: if pretty {
+                    JsonStyle::Pretty
+                } else if compact {
+                    JsonStyle::Compact
+                } else {
+                    JsonStyle::AsReceived
                 },
-            )
-            .await
+                bundle,
+                verbose: args.verbose,
+                brief: args.brief,
+                meta_urls,
+                mojang_meta_url,
+                locked_json,
+            };
+            if args.print_paths {
+                print_client_paths(&installation);
+                return Ok(());
+            }
+            if refresh_profiles_only {
+                installer::refresh_launcher_profile(installation)?;
+            } else {
+                installer::install_client(client, installation, Some(&report)).await?;
+            }
+            if launch {
+                launcher::open_launcher();
+            }
+            Ok(())
         }
         Subcommands::Server {
             no_launch_script,
             no_jar,
+            java,
+            launch_jar_name,
             install_dir,
+            output_dir,
+            mojang_meta_url,
+            systemd,
+            systemd_user,
+            all_scripts,
+            force,
+            shared_cache,
+            bundle,
+            readme,
+            no_readme,
         } => {
+            let write_readme = (readme || no_readme).then_some(readme);
+            if let MCVersionCLI::Custom(input) = &minecraft_version {
+                if input.contains(',') {
+                    if bundle.is_some() {
+                        bail!("A bundle can only install the Minecraft version it holds");
+                    }
+                    if args.update_lock {
+                        bail!("A lockfile can only pin a single Minecraft version");
+                    }
+                    return install_servers(
+                        &client,
+                        &meta_urls,
+                        input,
+                        args.loader_version,
+                        args.stable_only,
+                        &report,
+                        |minecraft_version, loader_version, dir| ServerInstallation {
+                            minecraft_version,
+                            loader_version,
+                            install_dir: install_dir.join(dir),
+                            output_dir: output_dir.as_ref().map(|output_dir| output_dir.join(dir)),
+                            download_jar: !no_jar,
+                            generate_script: !no_launch_script,
+                            all_scripts,
+                            force_download: force,
+                            shared_cache,
+                            java_path: java.clone(),
+                            launch_jar_name: launch_jar_name.clone(),
+                            mojang_meta_url: mojang_meta_url.clone(),
+                            systemd_service: systemd,
+                            systemd_user: systemd_user.clone(),
+                            bundle: None,
+                            meta_urls: meta_urls.clone(),
+                            write_readme,
+                            brief: args.brief,
+                            locked_json: None,
+                        },
+                    )
+                    .await;
+                }
+            }
+
+            if args.locked && bundle.is_some() {
+                bail!("--locked checks the lockfile against meta, so it needs network access");
+            }
+            let ((minecraft_version, loader_version), locked_json) = match &bundle {
+                Some(bundle) => (bundle_versions(bundle)?, None),
+                None if args.locked => {
+                    let locked = lockfile::read(&args.lockfile)?;
+                    let locked_json = LockedJson {
+                        sha256: locked.server_json_sha256,
+                        path: args.lockfile.clone(),
+                    };
+                    (
+                        (locked.minecraft_version, locked.loader_version),
+                        Some(locked_json),
+                    )
+                }
+                None => {
+                    let versions = get_versions(
+                        client.clone(),
+                        &meta_urls,
+                        minecraft_version,
+                        args.loader_version,
+                        args.stable_only,
+                        false,
+                        false,
+                    )
+                    .await?;
+                    if args.update_lock {
+                        update_lockfile(&client, &meta_urls, &args.lockfile, &versions, args.brief)
+                            .await?;
+                    }
+                    (versions, None)
+                }
+            };
             installer::install_server(
                 client,
                 ServerInstallation {
                     minecraft_version,
                     loader_version,
                     install_dir,
+                    output_dir,
                     download_jar: !no_jar,
                     generate_script: !no_launch_script,
+                    all_scripts,
+                    force_download: force,
+                    shared_cache,
+                    java_path: java,
+                    launch_jar_name,
+                    mojang_meta_url,
+                    systemd_service: systemd,
+                    systemd_user,
+                    bundle,
+                    meta_urls,
+                    write_readme,
+                    brief: args.brief,
+                    locked_json,
                 },
+                Some(&report),
             )
             .await
         }
+        Subcommands::Bundle {
+            output_dir,
+            mojang_meta_url,
+        } => {
+            let (minecraft_version, loader_version) = get_versions(
+                client.clone(),
+                &meta_urls,
+                minecraft_version,
+                args.loader_version,
+                args.stable_only,
+                false,
+                false,
+            )
+            .await?;
+            bundle::create(
+                client,
+                &meta_urls,
+                &output_dir,
+                minecraft_version,
+                loader_version,
+                &mojang_meta_url,
+                Some(&report),
+            )
+            .await
+        }
+        Subcommands::Check {
+            server,
+            install_dir,
+        } => {
+            // Checking stops short of writing anything, the offline caches included
+            cache::disable_writes();
+            let (minecraft_version, loader_version) = get_versions(
+                client.clone(),
+                &meta_urls,
+                minecraft_version,
+                args.loader_version,
+                args.stable_only,
+                false,
+                false,
+            )
+            .await?;
+            println!("Quilt meta is reachable");
+            if !installer::fetch_loader_versions_for(&client, &meta_urls, &minecraft_version)
+                .await?
+                .iter()
+                .any(|v| v.version == loader_version.version)
+            {
+                bail!(
+                    "Quilt Loader {loader_version} is not available for Minecraft {minecraft_version}"
+                );
+            }
+            println!(
+                "Quilt Loader {loader_version} is available for Minecraft {minecraft_version}"
+            );
+
+            if server {
+                let install_dir =
+                    install_dir.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+                if install_dir.exists() && !install_dir.is_dir() {
+                    bail!("{} is not a directory", install_dir.display());
+                }
+                println!("A server can be installed to {}", install_dir.display());
+            } else {
+                let install_dir = install_dir.unwrap_or_else(installer::detect_client_directory);
+                installer::verify_client_directory(&install_dir)?;
+                println!("{} is a valid launcher directory", install_dir.display());
+            }
+            println!("All checks passed.");
+            Ok(())
+        }
+        Subcommands::Resolve { format } => {
+            let (minecraft_version, loader_version) = get_versions(
+                client,
+                &meta_urls,
+                minecraft_version,
+                args.loader_version,
+                args.stable_only,
+                false,
+                false,
+            )
+            .await?;
+            match format {
+                ResolveFormat::Plain => println!("{minecraft_version} {loader_version}"),
+                ResolveFormat::Json => println!(
+                    "{}",
+                    serde_json::json!({
+                        "minecraft_version": minecraft_version.version,
+                        "loader_version": loader_version.version.to_string(),
+                    })
+                ),
+            }
+            Ok(())
+        }
+        Subcommands::Verify {
+            install_dir,
+            versions_dir,
+            profile,
+        } => installer::verify_client(
+            &install_dir,
+            &installer::resolve_versions_dir(&install_dir, versions_dir.as_deref()),
+            profile.as_deref(),
+        ),
+        Subcommands::Uninstall {
+            install_dir,
+            versions_dir,
+            profiles_file,
+            profile,
+            for_minecraft,
+            loader,
+            yes,
+        } => {
+            let versions_dir =
+                installer::resolve_versions_dir(&install_dir, versions_dir.as_deref());
+            let ids: Vec<String> = installer::installed_client_profiles(&versions_dir)?
+                .into_iter()
+                .filter(|installed| {
+                    (profile.is_none() || profile.as_ref() == Some(&installed.id))
+                        && (for_minecraft.is_none() || installed.minecraft_version == for_minecraft)
+                        && (loader.is_none() || installed.loader_version == loader)
+                })
+                .map(|installed| installed.id)
+                .collect();
+            if ids.is_empty() {
+                bail!(
+                    "No installed Quilt versions in {} match",
+                    versions_dir.display()
+                );
+            }
+
+            // A broad filter can match many installs, so they are listed before anything is removed
+            if ids.len() > 1 && !yes {
+                println!("This removes {} Quilt versions:", ids.len());
+                for id in &ids {
+                    println!("  {id}");
+                }
+                let confirmed = dialoguer::Confirm::new()
+                    .with_prompt("Remove them?")
+                    .default(false)
+                    .interact()
+                    .context("Could not ask to confirm, pass --yes to remove them anyway")?;
+                if !confirmed {
+                    println!("Nothing was removed.");
+                    return Ok(());
+                }
+            }
+            installer::uninstall_client(
+                &versions_dir,
+                &profiles_file.unwrap_or_else(|| install_dir.join("launcher_profiles.json")),
+                &ids,
+                args.brief,
+            )
+        }
+        Subcommands::ValidateProfiles { path } => installer::validate_launcher_profiles(&path),
+        Subcommands::List { versions, format } => {
+            let versions: Vec<(String, bool)> = match versions {
+                ListVersions::Minecraft => installer::fetch_minecraft_versions(client, &meta_urls)
+                    .await?
+                    .into_iter()
+                    .map(|v| (v.version, v.stable))
+                    .collect(),
+                ListVersions::Loader { for_minecraft } => {
+                    let loader_versions = match for_minecraft {
+                        Some(version) => {
+                            let minecraft_version =
+                                installer::fetch_minecraft_versions(client.clone(), &meta_urls)
+                                    .await?
+                                    .into_iter()
+                                    .find(|v| v.version == version)
+                                    .with_context(|| {
+                                        format!("Could not find Minecraft version {version}")
+                                    })?;
+                            installer::fetch_loader_versions_for(
+                                &client,
+                                &meta_urls,
+                                &minecraft_version,
+                            )
+                            .await?
+                        }
+                        None => installer::fetch_loader_versions(client, &meta_urls).await?,
+                    };
+                    loader_versions
+                        .into_iter()
+                        .map(|v| (v.version.to_string(), v.version.pre.is_empty()))
+                        .collect()
+                }
+            };
+            print_versions(&versions, format)
+        }
+        Subcommands::Describe { version } => describe(client, &meta_urls, version).await,
+        Subcommands::Config { .. } => unreachable!("handled before resolving versions"),
+        Subcommands::Cache { action } => match action {
+            CacheAction::Path => {
+                println!("{}", cache::get_cache_directory().display());
+                Ok(())
+            }
+            CacheAction::Clear { artifacts } => cache::clear(artifacts),
+        },
     }
 }
 
+/// Prints the installer version and the SHA-256 hash of the running executable
+pub fn print_self_hash() -> Result<()> {
+    let path = std::env::current_exe().context("Failed to find the installer executable")?;
+    let mut file =
+        std::fs::File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    println!(
+        "{} {}\nsha256: {:x}\npath: {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        hasher.finalize(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn print_client_paths(installation: &ClientInstallation) {
+    let profile_id = installation.profile_id();
+    let profile_dir = installation.resolve_versions_dir().join(&profile_id);
+    println!("install-dir: {}", installation.install_dir.display());
+    println!(
+        "versions-dir: {}",
+        installation.resolve_versions_dir().display()
+    );
+    println!(
+        "profile-json: {}",
+        profile_dir.join(format!("{profile_id}.json")).display()
+    );
+    println!(
+        "launcher-profiles: {}",
+        installation.resolve_profiles_file().display()
+    );
+}
+
+/// Prints versions and whether they are stable, the same way for Minecraft and Quilt Loader
+fn print_versions(versions: &[(String, bool)], format: ListFormat) -> Result<()> {
+    match format {
+        ListFormat::Plain => {
+            for (version, _) in versions {
+                println!("{version}");
+            }
+        }
+        ListFormat::Json => {
+            let versions: Vec<Value> = versions
+                .iter()
+                .map(
+                    |(version, stable)| serde_json::json!({ "version": version, "stable": stable }),
+                )
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&versions)?);
+        }
+        ListFormat::Csv => {
+            // Version numbers never contain commas or quotes, so nothing needs escaping
+            println!("version,stable");
+            for (version, stable) in versions {
+                println!("{version},{stable}");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn describe(client: Client, meta_urls: &[String], version: DescribeVersion) -> Result<()> {
+    match version {
+        DescribeVersion::Loader { version } => {
+            let loader_version = installer::fetch_loader_versions(client, meta_urls)
+                .await?
+                .into_iter()
+                .find(|v| loader_version_matches(&version, v))
+                .with_context(|| format!("Could not find Quilt Loader version {version}"))?;
+            println!("version: {}", loader_version.version);
+            println!("maven: {}", loader_version.maven);
+            println!("separator: {}", loader_version.separator);
+            println!("build: {}", loader_version.build);
+            println!("prerelease: {}", !loader_version.version.pre.is_empty());
+        }
+        DescribeVersion::Minecraft { version } => {
+            let minecraft_version = installer::fetch_minecraft_versions(client, meta_urls)
+                .await?
+                .into_iter()
+                .find(|v| v.version == version)
+                .with_context(|| format!("Could not find Minecraft version {version}"))?;
+            println!("version: {}", minecraft_version.version);
+            println!("stable: {}", minecraft_version.stable);
+        }
+    }
+    Ok(())
+}
+
+fn show_config(args: &Args) -> Result<()> {
+    let source = |id: &str| match args.value_sources.get(id).copied() {
+        Some(ValueSource::CommandLine) => "command line",
+        Some(ValueSource::EnvVariable) => "environment",
+        _ => "default",
+    };
+
+    let settings_path = settings::get_settings_path();
+    let settings_file: Map<String, Value> = std::fs::read_to_string(&settings_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    let settings_source = |key: &str| {
+        if settings_file.contains_key(key) {
+            "settings file"
+        } else {
+            "default"
+        }
+    };
+    let settings = settings::load();
+    let mojang_meta_url = std::env::var("QUILT_INSTALLER_MOJANG_META_URL");
+
+    match &args.minecraft_version_file {
+        Some(path) => println!("minecraft-version: read from {}", path.display()),
+        None => println!(
+            "minecraft-version: {} ({})",
+            args.minecraft_version,
+            source("minecraft_version")
+        ),
+    }
+    println!(
+        "loader-version: {} ({})",
+        args.loader_version,
+        source("loader_version")
+    );
+    println!(
+        "stable-only: {} ({})",
+        args.stable_only,
+        source("stable_only")
+    );
+    println!("ipv4: {} ({})", args.ipv4, source("ipv4"));
+    println!("ipv6: {} ({})", args.ipv6, source("ipv6"));
+    println!(
+        "pool-idle-timeout: {} ({})",
+        args.pool_idle_timeout,
+        source("pool_idle_timeout")
+    );
+    println!(
+        "pool-max-idle-per-host: {} ({})",
+        args.pool_max_idle_per_host
+            .map_or_else(|| "unlimited".into(), |count| count.to_string()),
+        source("pool_max_idle_per_host")
+    );
+    println!(
+        "connect-timeout: {} ({})",
+        args.connect_timeout,
+        source("connect_timeout")
+    );
+    println!(
+        "timeout: {} ({})",
+        args.timeout
+            .map_or_else(|| "unlimited".into(), |timeout| timeout.to_string()),
+        source("timeout")
+    );
+    println!(
+        "max-rate: {} ({})",
+        args.max_rate
+            .map_or_else(|| "unlimited".into(), |rate| rate.to_string()),
+        source("max_rate")
+    );
+    println!("retries: {} ({})", args.retries, source("retries"));
+    println!(
+        "user-agent: {} ({})",
+        args.user_agent(),
+        source("user_agent")
+    );
+    println!("scale: {} ({})", args.scale, source("scale"));
+    println!(
+        "meta-url: {} ({})",
+        args.meta_urls().join(", "),
+        if args.meta_url.is_empty() {
+            settings_source("meta_urls")
+        } else {
+            source("meta_url")
+        }
+    );
+    println!(
+        "mojang-meta-url: {} ({})",
+        mojang_meta_url
+            .as_deref()
+            .unwrap_or(installer::MOJANG_VERSION_MANIFEST_URL),
+        if mojang_meta_url.is_ok() {
+            "environment"
+        } else {
+            "default"
+        }
+    );
+    println!(
+        "client install-dir: {} (detected)",
+        installer::detect_client_directory().display()
+    );
+    println!(
+        "theme: {} ({})",
+        serde_json::to_value(settings.theme)?,
+        settings_source("theme")
+    );
+    println!(
+        "accent-color: {} ({})",
+        settings.accent_color.as_deref().unwrap_or("none"),
+        settings_source("accent_color")
+    );
+    println!(
+        "client-directory: {} ({})",
+        settings
+            .client_directory
+            .as_deref()
+            .map_or_else(|| "none".into(), |dir| dir.display().to_string()),
+        settings_source("client_directory")
+    );
+    println!("settings file: {}", settings_path.display());
+    println!(
+        "cache: {} ({})",
+        cache::get_cache_directory().display(),
+        if std::env::var_os("QUILT_INSTALLER_CACHE_DIR").is_some_and(|dir| !dir.is_empty()) {
+            "environment"
+        } else {
+            "default"
+        }
+    );
+    Ok(())
+}
+
+pub fn print_progress(progress: InstallProgress) {
+    if let InstallProgress::Indeterminate(step) = progress {
+        println!("{step}...");
+    }
+}
+
+/// Prints a progress event as a line of JSON to stderr, for wrapping tools to render
+fn print_json_progress(progress: InstallProgress) {
+    eprintln!("{}", progress_json(progress));
+}
+
+/// A progress event as JSON, with `fraction` null while the step can't tell how far along it is
+fn progress_json(progress: InstallProgress) -> Value {
+    let (step, fraction, completed) = match progress {
+        InstallProgress::Indeterminate(step) => (step, None, false),
+        InstallProgress::Determinate(step, fraction) => (step, Some(fraction), false),
+        InstallProgress::StepCompleted(step) => (step, Some(1.0), true),
+    };
+    let stage = match step {
+        InstallStep::DownloadProfileJson => "download-profile-json",
+        InstallStep::DownloadLibraries => "download-libraries",
+        InstallStep::DownloadServerJar => "download-server-jar",
+        InstallStep::UpdateLauncherProfiles => "update-launcher-profiles",
+    };
+    serde_json::json!({ "stage": stage, "fraction": fraction, "completed": completed })
+}
+
+/// Posts events to a progress webhook from a background task, so the install never waits on it
+struct Webhook {
+    events: UnboundedSender<Value>,
+    task: JoinHandle<()>,
+}
+
+impl Webhook {
+    fn start(client: Client, url: String) -> Self {
+        let (events, mut receiver) = mpsc::unbounded_channel::<Value>();
+        let task = tokio::spawn(async move {
+            let mut failures = 0;
+            let mut pending = None;
+            loop {
+                let next = match pending.take() {
+                    Some(event) => Some(event),
+                    None => receiver.recv().await,
+                };
+                let Some(mut event) = next else {
+                    break;
+                };
+                // Only the newest of a step's events is worth posting once the webhook is behind
+                while let Ok(newer) = receiver.try_recv() {
+                    if newer["stage"] == event["stage"] && event["completed"] == false {
+                        event = newer;
+                    } else {
+                        pending = Some(newer);
+                        break;
+                    }
+                }
+                let result = client
+                    .post(&url)
+                    .json(&event)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(error) = result {
+                    if failures == 0 {
+                        eprintln!("Failed to post progress to {url}: {error}");
+                    }
+                    failures += 1;
+                }
+            }
+            if failures > 1 {
+                eprintln!("{failures} progress events could not be posted to {url}");
+            }
+        });
+        Webhook { events, task }
+    }
+
+    /// Posts whether the install succeeded, then waits until every event has been posted
+    async fn finish(self, result: &Result<()>) {
+        let event = match result {
+            Ok(()) => serde_json::json!({ "stage": "done", "completed": true, "success": true }),
+            Err(error) => serde_json::json!({
+                "stage": "done",
+                "completed": true,
+                "success": false,
+                "error": format!("{error:#}"),
+            }),
+        };
+        let _ = self.events.send(event);
+        drop(self.events);
+        let _ = self.task.await;
+    }
+}
+
+/// Installs a server for each of a comma separated list of Minecraft versions,
+/// into directories named after them
+async fn install_servers(
+    client: &Client,
+    meta_urls: &[String],
+    minecraft_versions: &str,
+    loader_version: LoaderVersionCLI,
+    stable_only: bool,
+    report: &(dyn Fn(InstallProgress) + Sync),
+    installation: impl Fn(MinecraftVersion, LoaderVersion, &str) -> ServerInstallation,
+) -> Result<()> {
+    let versions: Vec<&str> = minecraft_versions
+        .split(',')
+        .map(str::trim)
+        .filter(|version| !version.is_empty())
+        .collect();
+
+    let mut failed = Vec::new();
+    for version in &versions {
+        let result = async {
+            let (minecraft_version, loader_version) = get_versions(
+                client.clone(),
+                meta_urls,
+                MCVersionCLI::Custom(version.to_string()),
+                loader_version.clone(),
+                stable_only,
+                false,
+                false,
+            )
+            .await?;
+            installer::install_server(
+                client.clone(),
+                installation(minecraft_version, loader_version, version),
+                Some(report),
+            )
+            .await
+        }
+        .await;
+        if let Err(error) = result {
+            eprintln!("Failed to install the server for Minecraft {version}: {error:#}");
+            failed.push(*version);
+        }
+    }
+
+    println!(
+        "Installed {} of {} servers",
+        versions.len() - failed.len(),
+        versions.len()
+    );
+    if !failed.is_empty() {
+        bail!(
+            "Could not install the servers for Minecraft {}",
+            failed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Only allows characters that are safe in a directory name on every platform
+fn parse_id_suffix(suffix: &str) -> Result<String, String> {
+    if suffix.is_empty() {
+        return Err("the suffix must not be empty".into());
+    }
+    if let Some(c) = suffix
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        return Err(format!(
+            "'{c}' is not allowed, only letters, digits, '-', '_' and '.' are"
+        ));
+    }
+    if suffix.ends_with('.') {
+        // Windows drops trailing dots from directory names
+        return Err("the suffix must not end with '.'".into());
+    }
+    Ok(suffix.to_owned())
+}
+
+/// Parses a loader build's maven coordinate, which needs a semver version like meta's do
+fn parse_loader_maven(coordinate: &str) -> Result<LoaderVersion, String> {
+    let parts: Vec<&str> = coordinate.split(':').collect();
+    let [group, artifact, version] = parts[..] else {
+        return Err("expected a maven coordinate like `org.quiltmc:quilt-loader:0.22.0`".into());
+    };
+    if group.is_empty() || artifact.is_empty() {
+        return Err("the group and artifact must not be empty".into());
+    }
+    let version = semver::Version::parse(version)
+        .map_err(|error| format!("{version} is not a semver version: {error}"))?;
+    Ok(LoaderVersion {
+        separator: '.',
+        build: 0,
+        maven: coordinate.to_owned(),
+        version,
+    })
+}
+
+fn parse_client_dir(dir: &str) -> Result<PathBuf, String> {
+    Ok(expand_launcher_dir(dir, installer::detect_client_directory))
+}
+
+/// Expands a leading `@launcher` to the launcher directory, leaving other paths as they are
+fn expand_launcher_dir(dir: &str, launcher_dir: impl FnOnce() -> PathBuf) -> PathBuf {
+    let Some(rest) = dir.strip_prefix("@launcher") else {
+        return dir.into();
+    };
+    if rest.is_empty() {
+        return launcher_dir();
+    }
+    match rest.strip_prefix(std::path::is_separator) {
+        Some(sub_dir) => launcher_dir().join(sub_dir),
+        // Something like `@launcher2` is a directory with that name
+        None => dir.into(),
+    }
+}
+
+fn read_minecraft_version_file(path: &Path) -> Result<MCVersionCLI> {
+    let version = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    let version = version.trim();
+    if version.is_empty() {
+        bail!("{} does not contain a Minecraft version", path.display());
+    }
+    Ok(MCVersionCLI::Custom(version.to_owned()))
+}
+
+fn bundle_versions(dir: &Path) -> Result<(MinecraftVersion, LoaderVersion)> {
+    let manifest = bundle::read_manifest(dir)?;
+    Ok((manifest.minecraft_version, manifest.loader_version))
+}
+
+async fn update_lockfile(
+    client: &Client,
+    meta_urls: &[String],
+    path: &Path,
+    (minecraft_version, loader_version): &(MinecraftVersion, LoaderVersion),
+    brief: bool,
+) -> Result<()> {
+    let locked = lockfile::resolve(
+        client,
+        meta_urls,
+        minecraft_version.clone(),
+        loader_version.clone(),
+    )
+    .await?;
+    lockfile::write(path, &locked)?;
+    if !brief {
+        println!(
+            "Locked Quilt Loader {loader_version} for Minecraft {minecraft_version} in {}",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
 async fn get_versions(
     client: Client,
+    meta_urls: &[String],
     minecraft_version: MCVersionCLI,
     loader_version: LoaderVersionCLI,
+    stable_only: bool,
+    offline: bool,
+    allow_unsupported: bool,
 ) -> Result<(MinecraftVersion, LoaderVersion)> {
-    let minecraft_versions = installer::fetch_minecraft_versions(client.clone()).await?;
-    let loader_versions = installer::fetch_loader_versions(client).await?;
+    let (minecraft_versions, loader_versions) = if offline {
+        (
+            installer::cached_minecraft_versions()?,
+            installer::cached_loader_versions()?,
+        )
+    } else {
+        (
+            installer::fetch_minecraft_versions(client.clone(), meta_urls).await?,
+            installer::fetch_loader_versions(client, meta_urls).await?,
+        )
+    };
 
-    Ok((
-        match minecraft_version {
-            MCVersionCLI::Stable => minecraft_versions.into_iter().find(|v| v.stable).unwrap(),
-            MCVersionCLI::Snapshot => minecraft_versions.into_iter().find(|v| !v.stable).unwrap(),
-            MCVersionCLI::Custom(input) => minecraft_versions
-                .into_iter()
-                .find(|v| v.version == input)
-                .with_context(|| format!("Could not find Minecraft version {}", input))?,
+    let minecraft_version = match minecraft_version {
+        MCVersionCLI::Stable => minecraft_versions.into_iter().find(|v| v.stable).unwrap(),
+        MCVersionCLI::Snapshot => minecraft_versions.into_iter().find(|v| !v.stable).unwrap(),
+        // Falls back to the latest stable version while meta doesn't recommend any
+        MCVersionCLI::Recommended => match minecraft_versions.iter().find(|v| v.recommended) {
+            Some(version) => version.clone(),
+            None => minecraft_versions.into_iter().find(|v| v.stable).unwrap(),
         },
+        MCVersionCLI::Custom(input) => {
+            match minecraft_versions.iter().find(|v| v.version == input) {
+                Some(version) => version.clone(),
+                None if allow_unsupported => {
+                    eprintln!(
+                        "Warning: Quilt does not support Minecraft {input} yet, installing it anyway"
+                    );
+                    MinecraftVersion {
+                        version: input,
+                        stable: false,
+                        recommended: false,
+                    }
+                }
+                None => {
+                    let suggestions = closest_versions(
+                        &input,
+                        minecraft_versions.iter().map(|v| v.version.as_str()),
+                    );
+                    if suggestions.is_empty() {
+                        bail!(
+                        "Could not find Minecraft version {}. It may be too old or not yet supported by Quilt",
+                        input
+                    )
+                    } else {
+                        bail!(
+                            "Could not find Minecraft version {}. Did you mean {}?",
+                            input,
+                            suggestions.join(", ")
+                        )
+                    }
+                }
+            }
+        }
+    };
+    if stable_only && !minecraft_version.stable {
+        bail!(
+            "Minecraft {} is a snapshot, but only stable versions are allowed",
+            minecraft_version
+        );
+    }
+
+    Ok((
+        minecraft_version,
         match loader_version {
             LoaderVersionCLI::Stable => loader_versions
                 .into_iter()
@@ -161,10 +1641,243 @@ async fn get_versions(
                 .into_iter()
                 .find(|v| !v.version.pre.is_empty())
                 .context("Could not find a beta Quilt Loader version")?,
-            LoaderVersionCLI::Custom(input) => loader_versions
-                .into_iter()
-                .find(|v| v.to_string() == input)
-                .with_context(|| format!("Could not find Quilt Loader version {}", input))?,
+            LoaderVersionCLI::Custom(input) => {
+                match loader_versions
+                    .iter()
+                    .find(|v| loader_version_matches(&input, v))
+                {
+                    Some(version) => version.clone(),
+                    None => newest_loader_version_starting_with(&input, loader_versions)?,
+                }
+            }
         },
     ))
 }
+
+/// Whether a version typed by the user names a loader version. Components are compared
+/// regardless of the separators between them, since `0.21.0-beta.1`, `0.21.0.beta.1` and
+/// `0.21.0+beta.1` are all written for the same version
+fn loader_version_matches(input: &str, loader_version: &LoaderVersion) -> bool {
+    let components = |version: &str| {
+        version
+            .split(['.', '-', '+'])
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    components(input.trim()) == components(&loader_version.version.to_string())
+}
+
+/// Whether a partial version like `0.21` is the start of a loader version,
+/// comparing whole components the same way as [`loader_version_matches`].
+/// A complete version like `0.21.0` only names itself, never one of its pre-releases
+fn loader_version_starts_with(input: &str, loader_version: &LoaderVersion) -> bool {
+    let input = input.trim();
+    let components: Vec<&str> = input.split('.').collect();
+    let partial = components.len() < 3
+        && components.iter().all(|component| {
+            !component.is_empty() && component.bytes().all(|b| b.is_ascii_digit())
+        });
+    if !partial {
+        return false;
+    }
+    let version = loader_version.version.to_string();
+    let mut version_components = version.split(['.', '-', '+']);
+    components
+        .into_iter()
+        .all(|component| version_components.next() == Some(component))
+}
+
+/// Resolves a partial loader version to the newest version starting with it,
+/// preferring stable versions. The other candidates are listed so the choice isn't a surprise
+fn newest_loader_version_starting_with(
+    input: &str,
+    loader_versions: Vec<LoaderVersion>,
+) -> Result<LoaderVersion> {
+    let mut candidates: Vec<LoaderVersion> = loader_versions
+        .into_iter()
+        .filter(|v| loader_version_starts_with(input, v))
+        .collect();
+    candidates.sort_by(|a, b| b.version.cmp(&a.version));
+    let chosen = candidates
+        .iter()
+        .find(|v| v.version.pre.is_empty())
+        .or_else(|| candidates.first())
+        .cloned()
+        .with_context(|| format!("Could not find Quilt Loader version {}", input))?;
+    if candidates.len() > 1 {
+        let others: Vec<String> = candidates
+            .iter()
+            .filter(|v| **v != chosen)
+            .map(ToString::to_string)
+            .collect();
+        eprintln!(
+            "{input} matches several Quilt Loader versions, using {chosen}. The others are {}",
+            others.join(", ")
+        );
+    }
+    Ok(chosen)
+}
+
+/// Picks up to three versions that look like what the user meant to type,
+/// preferring versions the input is a prefix of over ones that are merely a few edits away
+fn closest_versions<'a>(input: &str, versions: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut candidates: Vec<(usize, &str)> = versions
+        .filter_map(|version| {
+            if version.starts_with(input) {
+                Some((0, version))
+            } else {
+                let distance = edit_distance(input, version);
+                (distance <= 2).then_some((distance, version))
+            }
+        })
+        .collect();
+    // Stable, so versions keep meta's newest-first order within the same distance
+    candidates.sort_by_key(|(distance, _)| *distance);
+    candidates
+        .into_iter()
+        .take(3)
+        .map(|(_, version)| version)
+        .collect()
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loader(version: &str) -> LoaderVersion {
+        LoaderVersion {
+            separator: '.',
+            build: 0,
+            maven: format!("org.quiltmc:quilt-loader:{version}"),
+            version: semver::Version::parse(version).unwrap(),
+        }
+    }
+
+    #[test]
+    fn loader_version_matches_any_separator() {
+        let beta = loader("0.21.0-beta.1");
+        for input in [
+            "0.21.0-beta.1",
+            "0.21.0.beta.1",
+            "0.21.0+beta.1",
+            " 0.21.0-beta.1 ",
+        ] {
+            assert!(loader_version_matches(input, &beta), "{input}");
+        }
+    }
+
+    #[test]
+    fn loader_version_matches_only_the_same_version() {
+        let stable = loader("0.21.0");
+        assert!(loader_version_matches("0.21.0", &stable));
+        assert!(!loader_version_matches("0.21", &stable));
+        assert!(!loader_version_matches("0.21.0-beta.1", &stable));
+        assert!(!loader_version_matches("0.21.1", &stable));
+    }
+
+    #[test]
+    fn partial_loader_version_picks_the_newest_stable_match() {
+        let versions = vec![
+            loader("0.22.0-beta.1"),
+            loader("0.21.1-beta.2"),
+            loader("0.21.0"),
+            loader("0.21.1"),
+            loader("0.2.0"),
+        ];
+        let chosen = newest_loader_version_starting_with("0.21", versions.clone()).unwrap();
+        assert_eq!(chosen.version.to_string(), "0.21.1");
+        let chosen = newest_loader_version_starting_with("0.22", versions.clone()).unwrap();
+        assert_eq!(chosen.version.to_string(), "0.22.0-beta.1");
+        assert!(newest_loader_version_starting_with("0.23", versions).is_err());
+    }
+
+    #[test]
+    fn full_loader_version_is_not_resolved_to_a_pre_release() {
+        let versions = vec![loader("0.21.0-beta.1"), loader("0.21.0-beta.2")];
+        assert!(newest_loader_version_starting_with("0.21.0", versions.clone()).is_err());
+        assert!(newest_loader_version_starting_with("0.21.0-beta", versions.clone()).is_err());
+        let chosen = newest_loader_version_starting_with("0.21", versions).unwrap();
+        assert_eq!(chosen.version.to_string(), "0.21.0-beta.2");
+    }
+
+    #[test]
+    fn parse_loader_maven_needs_a_full_coordinate() {
+        let build = parse_loader_maven("org.quiltmc:quilt-loader:0.22.0-local").unwrap();
+        assert_eq!(build.version.to_string(), "0.22.0-local");
+        assert_eq!(build.maven, "org.quiltmc:quilt-loader:0.22.0-local");
+        assert!(parse_loader_maven("org.quiltmc:quilt-loader").is_err());
+        assert!(parse_loader_maven("org.quiltmc:quilt-loader:0.22").is_err());
+        assert!(parse_loader_maven(":quilt-loader:0.22.0").is_err());
+    }
+
+    #[test]
+    fn expand_launcher_dir_only_expands_the_token() {
+        let launcher = || PathBuf::from("/home/user/.minecraft");
+        assert_eq!(expand_launcher_dir("@launcher", launcher), launcher());
+        assert_eq!(
+            expand_launcher_dir("@launcher/test", launcher),
+            launcher().join("test")
+        );
+        assert_eq!(
+            expand_launcher_dir("@launcher2", launcher),
+            PathBuf::from("@launcher2")
+        );
+        assert_eq!(
+            expand_launcher_dir("instances/@launcher", launcher),
+            PathBuf::from("instances/@launcher")
+        );
+    }
+
+    #[test]
+    fn closest_versions_ranks_prefixes_then_edits() {
+        let versions = ["1.21", "1.20.4", "1.20.3", "1.20.2", "1.20.1", "1.19.2"];
+        let closest = |input| closest_versions(input, versions.iter().copied());
+
+        assert_eq!(closest("1.20.l"), ["1.20.4", "1.20.3", "1.20.2"]);
+        assert_eq!(
+            closest_versions("1.20.l", ["1.21", "1.20.1", "1.19.2"].into_iter()),
+            ["1.20.1"]
+        );
+        // Prefix matches come before closer edits, ties keep the newest-first order
+        assert_eq!(closest("1.2"), ["1.21", "1.20.4", "1.20.3"]);
+        assert_eq!(closest("1.20"), ["1.20.4", "1.20.3", "1.20.2"]);
+        assert!(closest("snapshot").is_empty());
+        assert!(closest("1.8.9").is_empty());
+    }
+
+    #[test]
+    fn edit_distance_counts_single_character_edits() {
+        assert_eq!(edit_distance("1.20.1", "1.20.1"), 0);
+        assert_eq!(edit_distance("1.20.l", "1.20.1"), 1);
+        assert_eq!(edit_distance("1.20", "1.20.1"), 2);
+        assert_eq!(edit_distance("", "1.20"), 4);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn parse_id_suffix_only_allows_safe_directory_names() {
+        assert_eq!(parse_id_suffix("test-1.2_b").unwrap(), "test-1.2_b");
+        assert!(parse_id_suffix("").is_err());
+        assert!(parse_id_suffix("a/b").is_err());
+        assert!(parse_id_suffix("a\\b").is_err());
+        assert!(parse_id_suffix("..").is_err());
+        assert!(parse_id_suffix("with space").is_err());
+        assert!(parse_id_suffix("caf\u{e9}").is_err());
+        assert!(parse_id_suffix("a:b").is_err());
+    }
+}
@@ -0,0 +1,49 @@
+use std::{backtrace::Backtrace, fmt::Display, fs, path::PathBuf};
+
+use anyhow::Result;
+use chrono::Utc;
+use native_dialog::{MessageDialog, MessageType};
+
+use crate::settings;
+
+/// Makes panics write a crash report, and point to it in a dialog, since the GUI otherwise
+/// just disappears. Nothing is sent anywhere
+pub fn install_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        let text = match write_report(info) {
+            Ok(path) => format!(
+                "The Quilt Installer crashed. A crash report was saved to {}, please attach it when reporting the issue.",
+                path.display()
+            ),
+            Err(error) => format!(
+                "The Quilt Installer crashed, and the crash report could not be saved: {error}"
+            ),
+        };
+        let _ = MessageDialog::new()
+            .set_title("Quilt Installer Crashed")
+            .set_text(&text)
+            .set_type(MessageType::Error)
+            .show_alert();
+    }));
+}
+
+fn write_report(panic: &dyn Display) -> Result<PathBuf> {
+    let now = Utc::now();
+    let dir = settings::get_config_directory().join("crash-reports");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("crash-{}.txt", now.format("%Y-%m-%d_%H.%M.%S")));
+    fs::write(
+        &path,
+        format!(
+            "Quilt Installer {} crashed at {}\nOS: {} {}\n\n{panic}\n\n{}\n",
+            env!("CARGO_PKG_VERSION"),
+            now.to_rfc3339(),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            Backtrace::force_capture(),
+        ),
+    )?;
+    Ok(path)
+}
@@ -1,51 +1,130 @@
+use std::any::TypeId;
 use std::borrow::Cow;
 use std::fmt::Debug;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
-use anyhow::{anyhow, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
 use iced::widget::{
-    Button, Checkbox, Column, PickList, ProgressBar, Radio, Row, Rule, Space, Text, TextInput,
+    tooltip, Button, Checkbox, Column, PickList, ProgressBar, Radio, Row, Rule, Scrollable, Space,
+    Text, TextInput, Tooltip,
 };
 use iced::{
-    alignment::Horizontal, executor, window, Application, Command, Element, Length, Settings, Theme,
+    alignment::Horizontal, executor, subscription, theme, theme::Palette, window, Alignment,
+    Application, Color, Command, Element, Length, Settings, Size, Subscription, Theme,
 };
 use native_dialog::{FileDialog, MessageDialog, MessageType};
-use png::Transformations;
 use reqwest::Client;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::sync::Mutex;
 
+use crate::icon;
 use crate::installer::{
-    fetch_loader_versions, fetch_minecraft_versions, get_default_client_directory, install_client,
-    install_server, ClientInstallation, Installation, LoaderVersion, MinecraftVersion,
-    ServerInstallation,
+    detect_client_directory, detect_launcher_profiles, fetch_loader_versions,
+    fetch_minecraft_versions, fetch_newer_installer_release, install_client, install_server,
+    ClientInstallation, InstallProgress, InstallStep, Installation, InstallerRelease, JsonStyle,
+    LoaderVersion, MinecraftVersion, OnConflict, ProfileType, ServerInstallation,
+    VersionUnavailable, MOJANG_VERSION_MANIFEST_URL, QUILT_META_URL,
 };
+use crate::java::{find_java_installations, JavaInstall};
+use crate::launcher;
+use crate::settings::{self, ThemeChoice, WindowGeometry};
+use crate::volume;
+
+pub fn run(client: Client, scale_factor: f64, meta_urls: Vec<String>) -> Result<()> {
+    if !scale_factor.is_finite() || scale_factor <= 0.0 {
+        bail!("The GUI scale must be a positive number");
+    }
+    if !has_display() {
+        bail!(
+            "There is no display to open the installer window on, like over SSH. {NO_WINDOW_HINT}"
+        );
+    }
+
+    let icon = create_icon();
+
+    let saved = settings::load().window;
+    let min_size = (
+        (WINDOW_WIDTH * scale_factor) as u32,
+        (WINDOW_HEIGHT * scale_factor) as u32,
+    );
+    // iced can't tell where the monitors are, so only positions that can't be on any are dropped
+    let position = saved
+        .filter(|saved| {
+            saved.x.abs() <= MAX_WINDOW_POSITION && saved.y.abs() <= MAX_WINDOW_POSITION
+        })
+        .map_or(window::Position::Default, |saved| {
+            window::Position::Specific(saved.x, saved.y)
+        });
 
-pub fn run(client: Client) -> Result<()> {
     State::run(Settings {
         window: window::Settings {
-            size: (600, 300),
-            resizable: false,
-            icon: Some(create_icon()?),
+            size: restored_window_size(saved, scale_factor),
+            position,
+            min_size: Some(min_size),
+            icon,
             ..Default::default()
         },
-        flags: client,
+        // The window geometry is saved before closing
+        exit_on_close_request: false,
+        // Identifies the app to the windowing system, rather than the binary name
+        id: Some("org.quiltmc.QuiltInstaller".into()),
+        flags: (client, scale_factor, meta_urls),
         ..Default::default()
+    })
+    .map_err(|error| match error {
+        iced::Error::WindowCreationFailed(_) | iced::Error::GraphicsCreationFailed(_) => {
+            anyhow!("Could not open the installer window: {error}. {NO_WINDOW_HINT}")
+        }
+        error => error.into(),
     })?;
 
     Ok(())
 }
 
-fn create_icon() -> Result<window::Icon> {
-    let mut decoder = png::Decoder::new(crate::ICON);
-    decoder.set_transformations(Transformations::EXPAND);
-    let mut reader = decoder.read_info()?;
-    let mut buffer = vec![0; reader.output_buffer_size()];
-    let info = reader.next_frame(&mut buffer)?;
-    let bytes = &buffer[..info.buffer_size()];
-    Ok(window::icon::from_rgba(
-        bytes.to_vec(),
-        info.width,
-        info.height,
-    )?)
+/// Where to point users who can't open the window, usually on a headless server
+const NO_WINDOW_HINT: &str = "Install from the terminal instead, with a subcommand like \
+    `quilt-installer server` or with `quilt-installer --tui`. Run with --help for more";
+
+/// Whether a display server is there to open windows on, missing in SSH sessions
+/// and on headless servers
+#[cfg(target_os = "linux")]
+fn has_display() -> bool {
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn has_display() -> bool {
+    true
+}
+
+/// The saved window size, kept between the default size and a size no monitor is larger than
+fn restored_window_size(saved: Option<WindowGeometry>, scale_factor: f64) -> (u32, u32) {
+    let (min_width, min_height) = (
+        (WINDOW_WIDTH * scale_factor) as u32,
+        (WINDOW_HEIGHT * scale_factor) as u32,
+    );
+    match saved {
+        Some(saved) => (
+            saved.width.clamp(min_width, MAX_WINDOW_SIZE.max(min_width)),
+            saved
+                .height
+                .clamp(min_height, MAX_WINDOW_SIZE.max(min_height)),
+        ),
+        None => (min_width, min_height),
+    }
+}
+
+/// The window icon, or `None` if it can't be loaded. A bundled icon that can't be decoded
+/// is already reported by [`icon::decoded`]
+fn create_icon() -> Option<window::Icon> {
+    let icon = icon::decoded()?;
+    window::icon::from_rgba(icon.rgba.clone(), icon.width, icon.height)
+        .map_err(|error| eprintln!("Failed to load the window icon: {error:?}"))
+        .ok()
 }
 
 #[derive(Debug, Default)]
@@ -60,34 +139,92 @@ struct State {
     selected_loader_version: Option<LoaderVersion>,
     show_betas: bool,
 
+    // Only list the first `recent_version_count` versions in both pickers
+    recent_only: bool,
+    recent_version_count: usize,
+
     installation_type: Installation,
 
     // Client settings
     client_location: PathBuf,
+    launcher_profiles_files: Vec<LauncherProfilesFile>,
+    selected_launcher_profiles_file: Option<LauncherProfilesFile>,
     generate_profile: bool,
+    profile_type: ProfileType,
+    open_launcher: bool,
+    // Empty for the launcher's default
+    game_dir: String,
+
+    // Why the selected location may be a bad place to install to
+    location_warning: Option<String>,
+    // Counts edits of the location fields, so only the last of a burst of keystrokes checks it
+    location_edits: u32,
 
     // Server settings
     server_location: PathBuf,
     download_server_jar: bool,
     generate_launch_script: bool,
+    java_installations: Vec<JavaInstall>,
+    selected_java: Option<JavaInstall>,
 
     // Progress information
     is_installing: bool,
-    progress: f32,
+    // None while the current step can't tell how far along it is
+    progress: Option<f32>,
+    // Where the indeterminate progress animation is, from 0 to 2 and back
+    indeterminate_phase: f32,
+    current_step: Option<InstallStep>,
+    progress_receiver: Option<Arc<Mutex<UnboundedReceiver<InstallProgress>>>>,
+    // Whether the running installations include a client, to open the launcher afterwards
+    installing_client: bool,
+
+    // Installations to run one after another with "Install all"
+    queue: Vec<PlannedInstallation>,
+    // Whether the running installations are the queue's, to take them off it as they succeed
+    installing_queue: bool,
+
+    // Why the version lists couldn't be fetched, until retried
+    meta_error: Option<String>,
+
+    // Newer installer release, until dismissed
+    newer_release: Option<InstallerRelease>,
+    // Whether the update check ran, so retrying the version lists doesn't run it again
+    checked_for_update: bool,
 
-    // HTTP reqwest client
+    // The window's last known position and size, to save when it is closed
+    window_position: Option<(i32, i32)>,
+    window_size: (u32, u32),
+
+    // Appearance
+    scale_factor: f64,
+    theme: ThemeChoice,
+    accent_color: Option<Color>,
+
+    // HTTP reqwest client and the Quilt meta mirrors it fetches from
     client: Client,
+    meta_urls: Vec<String>,
 }
 
 #[derive(Debug)]
 enum Message {
     Interaction(Interaction),
     Install,
+    InstallAll,
     BrowseClientLocation,
     BrowseServerLocation,
     SetMcVersions(Result<Vec<MinecraftVersion>>),
     SetLoaderVersions(Result<Vec<LoaderVersion>>),
-    DoneInstalling(Result<()>),
+    SetJavaInstallations(Vec<JavaInstall>),
+    SetNewerRelease(Result<Option<InstallerRelease>>),
+    Progress(InstallProgress),
+    AnimateProgress,
+    WindowMoved(i32, i32),
+    WindowResized(u32, u32),
+    CloseRequested,
+    /// Checks the location if it hasn't been edited again since the edit with this number
+    CheckLocation(u32),
+    /// How many of the started installations succeeded, and the error that stopped the rest
+    DoneInstalling(usize, Result<()>),
     Error(Error),
 }
 
@@ -96,18 +233,99 @@ enum Interaction {
     ChangeClientLocation(String),
     BrowseClientLocation,
     Install,
+    AddToQueue,
+    RemoveFromQueue(usize),
+    InstallAll,
     SelectInstallation(Installation),
     SelectLoaderVersion(LoaderVersion),
     SelectMcVersion(MinecraftVersion),
     SetShowSnapshots(bool),
     SetShowBetas(bool),
+    SetRecentOnly(bool),
     GenerateLaunchScript(bool),
     GenerateProfile(bool),
+    SelectProfileType(ProfileType),
+    OpenLauncher(bool),
+    ChangeGameDir(String),
     ChangeServerLocation(String),
     BrowseServerLocation,
     DownloadServerJar(bool),
+    SelectJava(JavaInstall),
+    OpenNewerRelease,
+    DismissNewerRelease,
+    RetryMetaFetch,
+    SelectLauncherProfilesFile(LauncherProfilesFile),
+}
+
+/// A detected launcher_profiles.json, labeled by its path to tell apart ones in the same directory
+#[derive(Debug, Clone, PartialEq, Eq, derive_more::Display)]
+#[display(fmt = "{}", "_0.display()")]
+struct LauncherProfilesFile(PathBuf);
+
+/// An installation set up in the GUI, either run right away or queued
+#[derive(Debug, Clone)]
+enum PlannedInstallation {
+    Client(ClientInstallation),
+    Server(ServerInstallation),
+}
+
+impl PlannedInstallation {
+    /// The steps the installation reports progress for, in the order it runs them
+    fn steps(&self) -> Vec<InstallStep> {
+        match self {
+            PlannedInstallation::Client(installation) => {
+                let mut steps = vec![InstallStep::DownloadProfileJson];
+                if installation.generate_profile {
+                    steps.push(InstallStep::UpdateLauncherProfiles);
+                }
+                steps
+            }
+            PlannedInstallation::Server(installation) => {
+                let mut steps = vec![InstallStep::DownloadLibraries];
+                if installation.download_jar {
+                    steps.push(InstallStep::DownloadServerJar);
+                }
+                steps
+            }
+        }
+    }
+
+    /// A one-line summary for the queue list
+    fn label(&self) -> String {
+        match self {
+            PlannedInstallation::Client(installation) => format!(
+                "Client: Minecraft {} with Quilt Loader {} in {}",
+                installation.minecraft_version,
+                installation.loader_version,
+                installation.install_dir.display()
+            ),
+            PlannedInstallation::Server(installation) => format!(
+                "Server: Minecraft {} with Quilt Loader {} in {}",
+                installation.minecraft_version,
+                installation.loader_version,
+                installation.install_dir.display()
+            ),
+        }
+    }
 }
 
+const WINDOW_WIDTH: f64 = 600.0;
+const WINDOW_HEIGHT: f64 = 380.0;
+/// Restored window sizes are capped at this, and positions further away are ignored
+const MAX_WINDOW_SIZE: u32 = 8192;
+const MAX_WINDOW_POSITION: i32 = 16384;
+/// How much taller the window gets while the queue list is shown
+const QUEUE_HEIGHT: f64 = 110.0;
+
+/// How many versions the pickers list with "Recent only", unless the settings say otherwise
+const DEFAULT_RECENT_VERSION_COUNT: usize = 20;
+
+/// How long to wait for each version list at startup before offering to retry,
+/// even if meta keeps the connection open
+const META_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long typing in a location field has to pause before the location is checked
+const LOCATION_CHECK_DELAY: Duration = Duration::from_millis(400);
+
 impl From<Message> for Command<Message> {
     fn from(m: Message) -> Self {
         Self::perform(async { m }, |t| t)
@@ -117,34 +335,73 @@ impl From<Message> for Command<Message> {
 impl Application for State {
     type Message = Message;
     type Executor = executor::Default;
-    type Flags = Client;
+    type Flags = (Client, f64, Vec<String>);
     type Theme = Theme;
 
     fn theme(&self) -> Self::Theme {
         use dark_light::Mode;
-        match dark_light::detect() {
-            Mode::Light => Theme::Light,
-            Mode::Dark | Mode::Default => Theme::Dark,
-        }
+        let palette = match self.theme {
+            ThemeChoice::System => match dark_light::detect() {
+                Mode::Light => Palette::LIGHT,
+                Mode::Dark | Mode::Default => Palette::DARK,
+            },
+            ThemeChoice::Light => Palette::LIGHT,
+            ThemeChoice::Dark => Palette::DARK,
+            ThemeChoice::HighContrast => HIGH_CONTRAST,
+        };
+        Theme::custom(Palette {
+            primary: self.accent_color.unwrap_or(palette.primary),
+            ..palette
+        })
     }
 
-    fn new(client: Client) -> (Self, Command<Self::Message>) {
+    fn new((client, scale_factor, meta_urls): Self::Flags) -> (Self, Command<Self::Message>) {
+        let settings = settings::load();
+        let accent_color = settings.accent_color.as_deref().and_then(|hex| {
+            let color = parse_hex_color(hex);
+            if color.is_none() {
+                eprintln!("Ignoring invalid accent color {hex}");
+            }
+            color
+        });
+
+        let mut state = State {
+            client_location: detect_client_directory(),
+            launcher_profiles_files: detect_launcher_profiles()
+                .into_iter()
+                .map(LauncherProfilesFile)
+                .collect(),
+            generate_profile: true,
+            server_location: std::env::current_dir().unwrap_or_default(),
+            download_server_jar: true,
+            generate_launch_script: true,
+            recent_only: settings.recent_versions_only,
+            recent_version_count: settings
+                .recent_version_count
+                .filter(|&count| count > 0)
+                .unwrap_or(DEFAULT_RECENT_VERSION_COUNT),
+            window_position: settings.window.map(|window| (window.x, window.y)),
+            window_size: restored_window_size(settings.window, scale_factor),
+            scale_factor,
+            theme: settings.theme,
+            accent_color,
+            client,
+            meta_urls,
+            ..Default::default()
+        };
+        state.update_location_warning();
+        let fetch_versions = state.fetch_versions();
         (
-            State {
-                client_location: get_default_client_directory(),
-                generate_profile: true,
-                server_location: std::env::current_dir().unwrap_or_default(),
-                download_server_jar: true,
-                generate_launch_script: true,
-                client: client.clone(),
-                ..Default::default()
-            },
+            state,
             Command::batch([
+                fetch_versions,
+                // Probing each installation runs `java -version`, which would block the executor
                 Command::perform(
-                    fetch_minecraft_versions(client.clone()),
-                    Message::SetMcVersions,
+                    tokio::task::spawn_blocking(find_java_installations),
+                    |installations| {
+                        Message::SetJavaInstallations(installations.unwrap_or_default())
+                    },
                 ),
-                Command::perform(fetch_loader_versions(client), Message::SetLoaderVersions),
             ]),
         )
     }
@@ -153,15 +410,42 @@ impl Application for State {
         "Quilt Installer".into()
     }
 
+    fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
             Message::Interaction(interaction) => match interaction {
                 Interaction::ChangeClientLocation(location) => {
                     self.client_location = location.into();
+                    self.selected_launcher_profiles_file = None;
+                    return self.check_location_soon();
                 }
                 Interaction::BrowseClientLocation => return Message::BrowseClientLocation.into(),
                 Interaction::Install => return Message::Install.into(),
-                Interaction::SelectInstallation(i) => self.installation_type = i,
+                Interaction::AddToQueue => match self.planned_installation() {
+                    Ok(installation) => {
+                        self.queue.push(installation);
+                        if self.queue.len() == 1 {
+                            return self.resize_window();
+                        }
+                    }
+                    Err(error) => return Message::Error(error).into(),
+                },
+                Interaction::RemoveFromQueue(index) => {
+                    if index < self.queue.len() {
+                        self.queue.remove(index);
+                    }
+                    if self.queue.is_empty() {
+                        return self.resize_window();
+                    }
+                }
+                Interaction::InstallAll => return Message::InstallAll.into(),
+                Interaction::SelectInstallation(i) => {
+                    self.installation_type = i;
+                    self.update_location_warning();
+                }
                 Interaction::SelectLoaderVersion(v) => self.selected_loader_version = Some(v),
                 Interaction::SelectMcVersion(v) => self.selected_minecraft_version = Some(v),
                 Interaction::SetShowSnapshots(enable) => {
@@ -180,18 +464,47 @@ impl Application for State {
                         .find(|v| enable || v.version.pre.is_empty())
                         .cloned();
                 }
+                Interaction::SetRecentOnly(enable) => {
+                    self.recent_only = enable;
+                    if let Err(error) = settings::update("recent_versions_only", enable) {
+                        eprintln!("Failed to save the version list setting: {error:#}");
+                    }
+                }
                 Interaction::GenerateLaunchScript(value) => self.generate_launch_script = value,
                 Interaction::GenerateProfile(value) => self.generate_profile = value,
+                Interaction::SelectProfileType(t) => self.profile_type = t,
+                Interaction::OpenLauncher(value) => self.open_launcher = value,
+                Interaction::ChangeGameDir(dir) => self.game_dir = dir,
                 Interaction::ChangeServerLocation(location) => {
                     self.server_location = location.into();
+                    return self.check_location_soon();
                 }
                 Interaction::BrowseServerLocation => return Message::BrowseServerLocation.into(),
                 Interaction::DownloadServerJar(value) => self.download_server_jar = value,
+                Interaction::SelectJava(java) => self.selected_java = Some(java),
+                Interaction::OpenNewerRelease => {
+                    if let Some(release) = &self.newer_release {
+                        if let Err(error) = launcher::open_url(&release.html_url) {
+                            return Message::Error(error.into()).into();
+                        }
+                    }
+                }
+                Interaction::DismissNewerRelease => self.newer_release = None,
+                Interaction::RetryMetaFetch => {
+                    self.meta_error = None;
+                    return self.fetch_versions();
+                }
+                Interaction::SelectLauncherProfilesFile(file) => {
+                    if let Some(dir) = file.0.parent() {
+                        self.client_location = dir.to_path_buf();
+                    }
+                    self.selected_launcher_profiles_file = Some(file);
+                }
             },
             Message::SetMcVersions(result) => {
                 match result {
                     Ok(versions) => self.minecraft_versions = versions,
-                    Err(error) => return Message::Error(error).into(),
+                    Err(error) => return self.meta_fetch_failed(error),
                 }
                 if self.selected_minecraft_version.is_none() {
                     self.selected_minecraft_version = self
@@ -200,11 +513,20 @@ impl Application for State {
                         .find(|v| self.show_snapshots || v.stable)
                         .cloned();
                 }
+                // Only checked once meta answered, so not while offline, and not with meta
+                // overridden, which usually means a mirror on a network without GitHub access
+                if !self.checked_for_update && self.meta_urls == [QUILT_META_URL] {
+                    self.checked_for_update = true;
+                    return Command::perform(
+                        fetch_newer_installer_release(self.client.clone()),
+                        Message::SetNewerRelease,
+                    );
+                }
             }
             Message::SetLoaderVersions(result) => {
                 match result {
                     Ok(versions) => self.loader_versions = versions,
-                    Err(error) => return Message::Error(error).into(),
+                    Err(error) => return self.meta_fetch_failed(error),
                 }
                 if self.selected_loader_version.is_none() {
                     self.selected_loader_version = self
@@ -214,6 +536,17 @@ impl Application for State {
                         .cloned();
                 }
             }
+            Message::SetJavaInstallations(installations) => {
+                self.java_installations = installations;
+                if self.selected_java.is_none() {
+                    self.selected_java = self.java_installations.first().cloned();
+                }
+            }
+            Message::SetNewerRelease(result) => match result {
+                Ok(release) => self.newer_release = release,
+                // Not being able to check for updates shouldn't get in the way of installing
+                Err(error) => eprintln!("Failed to check for installer updates: {error:?}"),
+            },
             Message::BrowseClientLocation => {
                 let mut dialog = FileDialog::new();
                 let working_dir = std::env::current_dir();
@@ -223,7 +556,11 @@ impl Application for State {
                     dialog = dialog.set_location(working_dir)
                 }
                 match dialog.show_open_single_dir() {
-                    Ok(Some(path)) => self.client_location = path,
+                    Ok(Some(path)) => {
+                        self.client_location = path;
+                        self.selected_launcher_profiles_file = None;
+                        self.update_location_warning();
+                    }
                     Ok(None) => (),
                     Err(error) => return Message::Error(error.into()).into(),
                 }
@@ -237,88 +574,97 @@ impl Application for State {
                     dialog = dialog.set_location(working_dir)
                 }
                 match dialog.show_open_single_dir() {
-                    Ok(Some(path)) => self.server_location = path,
+                    Ok(Some(path)) => {
+                        self.server_location = path;
+                        self.update_location_warning();
+                    }
                     Ok(None) => (),
                     Err(error) => return Message::Error(error.into()).into(),
                 }
             }
-            Message::Install => {
-                self.is_installing = true;
-                self.progress = 0.0;
-
-                return match self.installation_type {
-                    Installation::Client => Command::perform(
-                        install_client(
-                            self.client.clone(),
-                            ClientInstallation {
-                                minecraft_version: match &self.selected_minecraft_version {
-                                    Some(s) => s.clone(),
-                                    None => {
-                                        return Message::Error(anyhow!(
-                                            "Minecraft version not selected!"
-                                        ))
-                                        .into()
-                                    }
-                                },
-                                loader_version: match &self.selected_loader_version {
-                                    Some(s) => s.clone(),
-                                    None => {
-                                        return Message::Error(anyhow!(
-                                            "Loader version not selected!"
-                                        ))
-                                        .into()
-                                    }
-                                },
-                                install_dir: self.client_location.clone(),
-                                generate_profile: self.generate_profile,
-                            },
-                        ),
-                        Message::DoneInstalling,
-                    ),
-                    Installation::Server => Command::perform(
-                        install_server(
-                            self.client.clone(),
-                            ServerInstallation {
-                                minecraft_version: match &self.selected_minecraft_version {
-                                    Some(s) => s.clone(),
-                                    None => {
-                                        return Message::Error(anyhow!(
-                                            "Minecraft version not selected!"
-                                        ))
-                                        .into()
-                                    }
-                                },
-                                loader_version: match &self.selected_loader_version {
-                                    Some(s) => s.clone(),
-                                    None => {
-                                        return Message::Error(anyhow!(
-                                            "Loader version not selected!"
-                                        ))
-                                        .into()
-                                    }
-                                },
-                                install_dir: self.server_location.clone(),
-                                download_jar: self.download_server_jar,
-                                generate_script: self.generate_launch_script,
-                            },
-                        ),
-                        Message::DoneInstalling,
-                    ),
+            Message::Install => match self.planned_installation() {
+                Ok(installation) => {
+                    self.installing_queue = false;
+                    return self.start_installing(vec![installation]);
+                }
+                Err(error) => return Message::Error(error).into(),
+            },
+            Message::InstallAll => {
+                // The queue stays as it is while installing, so whatever doesn't get installed is
+                // still there to retry
+                self.installing_queue = true;
+                return self.start_installing(self.queue.clone());
+            }
+            Message::Progress(progress) => match progress {
+                InstallProgress::Indeterminate(step) => {
+                    self.current_step = Some(step);
+                    self.progress = None;
+                }
+                InstallProgress::Determinate(step, fraction) => {
+                    self.current_step = Some(step);
+                    self.progress = Some(fraction);
+                }
+                InstallProgress::StepCompleted(_) => self.progress = Some(1.0),
+            },
+            Message::AnimateProgress => {
+                self.indeterminate_phase = (self.indeterminate_phase + 0.04) % 2.0;
+            }
+            Message::CheckLocation(edit) => {
+                if edit == self.location_edits {
+                    self.update_location_warning();
+                }
+            }
+            Message::WindowMoved(x, y) => self.window_position = Some((x, y)),
+            Message::WindowResized(width, height) => self.window_size = (width, height),
+            Message::CloseRequested => {
+                let (width, mut height) = self.window_size;
+                if !self.queue.is_empty() {
+                    // The queue isn't kept, so neither is the room it took up
+                    height = height.saturating_sub((QUEUE_HEIGHT * self.scale_factor) as u32);
+                }
+                let (x, y) = self.window_position.unwrap_or_default();
+                let geometry = WindowGeometry {
+                    x,
+                    y,
+                    width,
+                    height,
                 };
+                if let Err(error) = settings::update("window", geometry) {
+                    eprintln!("Failed to save the window position: {error:#}");
+                }
+                return window::close();
             }
-            Message::DoneInstalling(res) => {
+            Message::DoneInstalling(installed, res) => {
                 self.is_installing = false;
-                self.progress = 1.0;
+                self.progress = Some(1.0);
+                self.current_step = None;
+                self.progress_receiver = None;
 
-                if let Err(e) = res {
-                    return Message::Error(e).into();
+                let mut commands = Vec::new();
+                if self.installing_queue {
+                    self.queue.drain(..installed.min(self.queue.len()));
+                    if self.queue.is_empty() {
+                        commands.push(self.resize_window());
+                    }
+                }
+                match res {
+                    Err(e) => commands.push(Message::Error(e).into()),
+                    Ok(()) if self.installing_client && self.open_launcher => {
+                        launcher::open_launcher()
+                    }
+                    Ok(()) => (),
                 }
+                return Command::batch(commands);
             }
             Message::Error(error) => {
                 eprintln!("{error:?}");
+                let text = match error_hint(&error) {
+                    Some(hint) => format!("{error}\n\n{hint}"),
+                    None => error.to_string(),
+                };
                 MessageDialog::new()
                     .set_title("Quilt Installer Error")
-                    .set_text(&error.to_string())
+                    .set_text(&text)
                     .set_type(MessageType::Error)
                     .show_alert()
                     .unwrap();
@@ -328,6 +674,38 @@ impl Application for State {
         Command::none()
     }
 
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let animation = if self.is_installing && self.progress.is_none() {
+            iced::time::every(Duration::from_millis(30)).map(|_| Message::AnimateProgress)
+        } else {
+            Subscription::none()
+        };
+        let progress = match &self.progress_receiver {
+            Some(receiver) => subscription::unfold(
+                TypeId::of::<InstallProgress>(),
+                receiver.clone(),
+                |receiver| async move {
+                    let progress = receiver.lock().await.recv().await;
+                    match progress {
+                        Some(progress) => (Message::Progress(progress), receiver),
+                        // The installation finished, wait for the subscription to be dropped
+                        None => std::future::pending().await,
+                    }
+                },
+            ),
+            None => Subscription::none(),
+        };
+        let window_events = subscription::events_with(|event, _| match event {
+            iced::Event::Window(window::Event::Moved { x, y }) => Some(Message::WindowMoved(x, y)),
+            iced::Event::Window(window::Event::Resized { width, height }) => {
+                Some(Message::WindowResized(width, height))
+            }
+            iced::Event::Window(window::Event::CloseRequested) => Some(Message::CloseRequested),
+            _ => None,
+        });
+        Subscription::batch([animation, progress, window_events])
+    }
+
     fn view(&self) -> Element<'_, Self::Message> {
         let installation_label = Text::new("Installation:").width(140);
         let installation_client = Radio::new(
@@ -356,6 +734,7 @@ impl Application for State {
                 self.minecraft_versions
                     .iter()
                     .filter(|v| self.show_snapshots || v.stable)
+                    .take(self.version_list_limit())
                     .cloned(),
             ),
             self.selected_minecraft_version.clone(),
@@ -369,9 +748,16 @@ impl Application for State {
         );
         let mc_row = Row::new()
             .push(minecraft_version_label)
-            .push(minecraft_version_list)
+            .push(with_tooltip(
+                minecraft_version_list,
+                "The Minecraft version to install Quilt Loader for",
+            ))
             .push(Space::new(20, 0))
-            .push(enable_snapshots)
+            .push(with_tooltip(
+                enable_snapshots,
+                "Snapshots are unstable previews of upcoming Minecraft versions.\n\
+                They can corrupt worlds and most mods don't support them.",
+            ))
             .width(Length::Fill)
             .spacing(5)
             .padding(5);
@@ -382,6 +768,7 @@ impl Application for State {
                 self.loader_versions
                     .iter()
                     .filter(|v| self.show_betas || v.version.pre.is_empty())
+                    .take(self.version_list_limit())
                     .cloned(),
             ),
             self.selected_loader_version.clone(),
@@ -389,11 +776,24 @@ impl Application for State {
         )
         .width(200);
         let enable_betas = Checkbox::new("Show betas", self.show_betas, Interaction::SetShowBetas);
+        let recent_only =
+            Checkbox::new("Recent only", self.recent_only, Interaction::SetRecentOnly);
         let loader_row = Row::new()
             .push(loader_version_label)
-            .push(loader_version_list)
+            .push(with_tooltip(
+                loader_version_list,
+                "The Quilt Loader version to install, the latest stable one is recommended",
+            ))
             .push(Space::new(20, 0))
-            .push(enable_betas)
+            .push(with_tooltip(
+                enable_betas,
+                "Betas get new Quilt Loader features before stable releases do,\n\
+                but may contain bugs that break your game.",
+            ))
+            .push(with_tooltip(
+                recent_only,
+                "Only list the most recent versions, which makes the lists faster to scroll",
+            ))
             .width(Length::Fill)
             .spacing(5)
             .padding(5);
@@ -418,15 +818,69 @@ impl Application for State {
             .spacing(5)
             .padding(5);
 
+        // Only worth asking when there is more than one to choose from
+        let launcher_profiles_row = (self.launcher_profiles_files.len() > 1).then(|| {
+            let label = Text::new("Profiles file:").width(140);
+            let list = PickList::new(
+                &self.launcher_profiles_files[..],
+                self.selected_launcher_profiles_file.clone(),
+                Interaction::SelectLauncherProfilesFile,
+            )
+            .placeholder("launcher_profiles.json in the directory")
+            .width(Length::Fill);
+            Row::new()
+                .push(label)
+                .push(list)
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+        });
+
         let client_options_label = Text::new("Options:").width(140);
         let create_profile = Checkbox::new(
             "Generate profile",
             self.generate_profile,
             Interaction::GenerateProfile,
         );
+        let profile_type_label = Text::new("Profile type:");
+        let profile_type_list = PickList::new(
+            ProfileType::ALL,
+            Some(self.profile_type),
+            Interaction::SelectProfileType,
+        );
         let client_options_row = Row::new()
             .push(client_options_label)
             .push(create_profile)
+            .push(Space::new(35, 0))
+            .push(profile_type_label)
+            .push(profile_type_list)
+            .align_items(Alignment::Center)
+            .spacing(5)
+            .padding(5);
+        let open_launcher = Checkbox::new(
+            "Open launcher after install",
+            self.open_launcher,
+            Interaction::OpenLauncher,
+        );
+        let game_dir_label = Text::new("Game directory:").width(140);
+        let mut game_dir_input =
+            TextInput::new("The launcher's default", &self.game_dir).padding(5);
+        if !self.is_installing {
+            game_dir_input = game_dir_input.on_input(Interaction::ChangeGameDir);
+        }
+        let game_dir_row = Row::new()
+            .push(game_dir_label)
+            .push(with_tooltip(
+                game_dir_input,
+                "A separate directory for this profile's mods, worlds and settings",
+            ))
+            .width(Length::Fill)
+            .spacing(5)
+            .padding(5);
+
+        let client_launch_row = Row::new()
+            .push(Space::new(140, 0))
+            .push(open_launcher)
             .spacing(5)
             .padding(5);
 
@@ -469,29 +923,407 @@ impl Application for State {
             .spacing(5)
             .padding(5);
 
-        let mut column = Column::new()
-            .padding(5)
+        let java_label = Text::new("Java:").width(140);
+        let java_list = PickList::new(
+            Cow::from(&self.java_installations[..]),
+            self.selected_java.clone(),
+            Interaction::SelectJava,
+        )
+        .placeholder("java (from PATH)")
+        .width(Length::Fill);
+        let java_row = Row::new()
+            .push(java_label)
+            .push(java_list)
+            .width(Length::Fill)
             .spacing(5)
+            .padding(5);
+
+        let mut column = Column::new().padding(5).spacing(5);
+
+        if let Some(error) = &self.meta_error {
+            let error_text =
+                Text::new(format!("Failed to fetch the versions: {error}")).width(Length::Fill);
+            let retry = Button::new(Text::new("Retry")).on_press(Interaction::RetryMetaFetch);
+            let error_row = Row::new()
+                .push(error_text)
+                .push(retry)
+                .width(Length::Fill)
+                .spacing(5)
+                .padding(5);
+            column = column.push(error_row).push(Rule::horizontal(5));
+        }
+
+        if let Some(release) = &self.newer_release {
+            let release_text =
+                Text::new(format!("Quilt Installer {} is available", release.tag_name))
+                    .width(Length::Fill);
+            let open = Button::new(Text::new("Open")).on_press(Interaction::OpenNewerRelease);
+            let dismiss =
+                Button::new(Text::new("Dismiss")).on_press(Interaction::DismissNewerRelease);
+            let release_row = Row::new()
+                .push(release_text)
+                .push(open)
+                .push(dismiss)
+                .width(Length::Fill)
+                .spacing(5)
+                .padding(5);
+            column = column.push(release_row).push(Rule::horizontal(5));
+        }
+
+        column = column
             .push(installation_row)
             .push(mc_row)
             .push(loader_row)
             .push(Rule::horizontal(5));
 
+        let location_warning_row = self.location_warning.as_ref().map(|warning| {
+            Row::new()
+                .push(Space::new(140, 0))
+                .push(
+                    Text::new(format!("Warning: {warning}"))
+                        .style(self.theme().palette().danger)
+                        .width(Length::Fill),
+                )
+                .spacing(5)
+                .padding(5)
+        });
+
         column = match self.installation_type {
-            Installation::Client => column.push(client_location_row).push(client_options_row),
-            Installation::Server => column.push(server_location_row).push(server_options_row),
+            Installation::Client => {
+                column = column.push(client_location_row);
+                if let Some(location_warning_row) = location_warning_row {
+                    column = column.push(location_warning_row);
+                }
+                if let Some(launcher_profiles_row) = launcher_profiles_row {
+                    column = column.push(launcher_profiles_row);
+                }
+                column
+                    .push(client_options_row)
+                    .push(game_dir_row)
+                    .push(client_launch_row)
+            }
+            Installation::Server => {
+                column = column.push(server_location_row);
+                if let Some(location_warning_row) = location_warning_row {
+                    column = column.push(location_warning_row);
+                }
+                column.push(server_options_row).push(java_row)
+            }
         };
 
-        let button_label = Text::new("Install")
-            .horizontal_alignment(Horizontal::Center)
-            .width(Length::Fill);
+        let button_label = Text::new(match self.current_step {
+            Some(step) => format!("{step}..."),
+            None => "Install".into(),
+        })
+        .horizontal_alignment(Horizontal::Center)
+        .width(Length::Fill);
         let mut button = Button::new(button_label).width(Length::Fill);
+        let mut queue_button = Button::new(Text::new("Add to queue"));
         if !self.is_installing {
             button = button.on_press(Interaction::Install);
+            queue_button = queue_button.on_press(Interaction::AddToQueue);
+        }
+        let button_row = Row::new()
+            .push(button)
+            .push(queue_button)
+            .width(Length::Fill)
+            .spacing(5);
+        // iced has no indeterminate progress bar, so one that fills and empties stands in for it
+        let progress = ProgressBar::new(
+            0.0..=1.0,
+            match self.progress {
+                Some(progress) => progress,
+                None if self.is_installing => 1.0 - (self.indeterminate_phase - 1.0).abs(),
+                None => 0.0,
+            },
+        );
+        column = column.push(button_row).push(progress);
+
+        if !self.queue.is_empty() {
+            let mut queue_list = Column::new().spacing(5).padding(5);
+            for (index, installation) in self.queue.iter().enumerate() {
+                let mut remove = Button::new(Text::new("Remove"));
+                if !self.is_installing {
+                    remove = remove.on_press(Interaction::RemoveFromQueue(index));
+                }
+                queue_list = queue_list.push(
+                    Row::new()
+                        .push(Text::new(installation.label()).width(Length::Fill))
+                        .push(remove)
+                        .spacing(5)
+                        .align_items(Alignment::Center),
+                );
+            }
+            let mut install_all = Button::new(
+                Text::new(format!("Install all ({})", self.queue.len()))
+                    .horizontal_alignment(Horizontal::Center)
+                    .width(Length::Fill),
+            )
+            .width(Length::Fill);
+            if !self.is_installing {
+                install_all = install_all.on_press(Interaction::InstallAll);
+            }
+            column = column
+                .push(Rule::horizontal(5))
+                .push(Scrollable::new(queue_list).height(Length::Fill))
+                .push(install_all);
         }
-        let progress = ProgressBar::new(0.0..=1.0, self.progress);
-        column = column.push(button).push(progress);
 
         Element::from(column).map(Message::Interaction)
     }
 }
+
+const HIGH_CONTRAST: Palette = Palette {
+    background: Color::BLACK,
+    text: Color::WHITE,
+    primary: Color::from_rgb(1.0, 0.85, 0.0),
+    success: Color::from_rgb(0.0, 1.0, 0.4),
+    danger: Color::from_rgb(1.0, 0.3, 0.3),
+};
+
+impl State {
+    /// How many versions the pickers list, after hiding snapshots and betas
+    fn version_list_limit(&self) -> usize {
+        if self.recent_only {
+            self.recent_version_count
+        } else {
+            usize::MAX
+        }
+    }
+
+    /// Checks the location once typing in its field pauses, rather than on every keystroke,
+    /// as the check reads the mount table and resolves the path
+    fn check_location_soon(&mut self) -> Command<Message> {
+        self.location_edits = self.location_edits.wrapping_add(1);
+        let edit = self.location_edits;
+        Command::perform(tokio::time::sleep(LOCATION_CHECK_DELAY), move |_| {
+            Message::CheckLocation(edit)
+        })
+    }
+
+    /// Checks whether the location of the selected installation type is on a network share
+    /// or removable drive. Done when the location changes rather than on every redraw
+    fn update_location_warning(&mut self) {
+        self.location_warning = volume::install_dir_warning(match self.installation_type {
+            Installation::Client => &self.client_location,
+            Installation::Server => &self.server_location,
+        });
+    }
+
+    /// Builds the installation the current settings describe
+    fn planned_installation(&self) -> Result<PlannedInstallation> {
+        let minecraft_version = self
+            .selected_minecraft_version
+            .clone()
+            .context("Minecraft version not selected!")?;
+        let loader_version = self
+            .selected_loader_version
+            .clone()
+            .context("Loader version not selected!")?;
+
+        Ok(match self.installation_type {
+            Installation::Client => PlannedInstallation::Client(ClientInstallation {
+                minecraft_version,
+                loader_version,
+                install_dir: self.client_location.clone(),
+                versions_dir: None,
+                generate_profile: self.generate_profile,
+                profile_type: self.profile_type,
+                offline: false,
+                profiles_file: self
+                    .selected_launcher_profiles_file
+                    .as_ref()
+                    .map(|file| file.0.clone()),
+                id_suffix: None,
+                keep_unknown_files: false,
+                placeholder_jar: false,
+                game_dir: (!self.game_dir.trim().is_empty())
+                    .then(|| PathBuf::from(self.game_dir.trim())),
+                allow_unsupported: false,
+                exclude_libraries: Vec::new(),
+                maven_loader: None,
+                on_conflict: OnConflict::Overwrite,
+                canonical: false,
+                deterministic: false,
+                json_style: JsonStyle::AsReceived,
+                bundle: None,
+                verbose: false,
+                brief: false,
+                meta_urls: self.meta_urls.clone(),
+                mojang_meta_url: MOJANG_VERSION_MANIFEST_URL.into(),
+                locked_json: None,
+            }),
+            Installation::Server => PlannedInstallation::Server(ServerInstallation {
+                minecraft_version,
+                loader_version,
+                install_dir: self.server_location.clone(),
+                output_dir: None,
+                download_jar: self.download_server_jar,
+                generate_script: self.generate_launch_script,
+                all_scripts: false,
+                force_download: false,
+                shared_cache: false,
+                java_path: self.selected_java.as_ref().map(|j| j.path.clone()),
+                launch_jar_name: None,
+                mojang_meta_url: MOJANG_VERSION_MANIFEST_URL.into(),
+                systemd_service: false,
+                systemd_user: None,
+                bundle: None,
+                meta_urls: self.meta_urls.clone(),
+                write_readme: None,
+                brief: false,
+                locked_json: None,
+            }),
+        })
+    }
+
+    /// Runs the installations one after another, sharing the progress bar between them, and stops
+    /// at the first one that fails
+    fn start_installing(&mut self, installations: Vec<PlannedInstallation>) -> Command<Message> {
+        self.is_installing = true;
+        self.progress = None;
+        self.installing_client = installations
+            .iter()
+            .any(|installation| matches!(installation, PlannedInstallation::Client(_)));
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.progress_receiver = Some(Arc::new(Mutex::new(receiver)));
+        let client = self.client.clone();
+
+        Command::perform(
+            async move {
+                let count = installations.len() as f32;
+                for (index, installation) in installations.into_iter().enumerate() {
+                    // Each installation fills its own slice of the progress bar, and each of its
+                    // steps a part of that slice
+                    let steps = installation.steps();
+                    let position = |step: InstallStep, fraction: f32| {
+                        let done = steps.iter().position(|&s| s == step).unwrap_or(0) as f32;
+                        let slice = (done + fraction) / steps.len() as f32;
+                        (index as f32 + slice) / count
+                    };
+                    let report = |progress: InstallProgress| {
+                        let progress = match progress {
+                            InstallProgress::Determinate(step, fraction) => {
+                                InstallProgress::Determinate(step, position(step, fraction))
+                            }
+                            InstallProgress::StepCompleted(step) => {
+                                InstallProgress::Determinate(step, position(step, 1.0))
+                            }
+                            progress => progress,
+                        };
+                        let _ = sender.send(progress);
+                    };
+                    let result = match installation {
+                        PlannedInstallation::Client(installation) => {
+                            install_client(client.clone(), installation, Some(&report)).await
+                        }
+                        PlannedInstallation::Server(installation) => {
+                            install_server(client.clone(), installation, Some(&report)).await
+                        }
+                    };
+                    if let Err(error) = result {
+                        return (index, Err(error));
+                    }
+                }
+                (count as usize, Ok(()))
+            },
+            |(installed, result)| Message::DoneInstalling(installed, result),
+        )
+    }
+
+    /// Grows the window when the queue list was just shown, and shrinks it when it was hidden
+    fn resize_window(&self) -> Command<Message> {
+        let (width, height) = self.window_size;
+        let queue_height = (QUEUE_HEIGHT * self.scale_factor) as u32;
+        let height = if self.queue.is_empty() {
+            height
+                .saturating_sub(queue_height)
+                .max((WINDOW_HEIGHT * self.scale_factor) as u32)
+        } else {
+            height + queue_height
+        };
+        window::resize(Size::new(width, height))
+    }
+
+    /// Fetches the Minecraft and loader version lists from meta, giving up after a timeout
+    fn fetch_versions(&self) -> Command<Message> {
+        let timed_out = || anyhow!("Quilt meta did not respond in time");
+        Command::batch([
+            Command::perform(
+                {
+                    let (client, meta_urls) = (self.client.clone(), self.meta_urls.clone());
+                    async move {
+                        tokio::time::timeout(
+                            META_FETCH_TIMEOUT,
+                            fetch_minecraft_versions(client, &meta_urls),
+                        )
+                        .await
+                        .unwrap_or_else(|_| Err(timed_out()))
+                    }
+                },
+                Message::SetMcVersions,
+            ),
+            Command::perform(
+                {
+                    let (client, meta_urls) = (self.client.clone(), self.meta_urls.clone());
+                    async move {
+                        tokio::time::timeout(
+                            META_FETCH_TIMEOUT,
+                            fetch_loader_versions(client, &meta_urls),
+                        )
+                        .await
+                        .unwrap_or_else(|_| Err(timed_out()))
+                    }
+                },
+                Message::SetLoaderVersions,
+            ),
+        ])
+    }
+
+    /// Shows the failure next to a retry button rather than leaving the pickers empty
+    fn meta_fetch_failed(&mut self, error: Error) -> Command<Message> {
+        eprintln!("{error:?}");
+        // Both lists failing at once is usually one outage, so keep the first error
+        self.meta_error.get_or_insert_with(|| error.to_string());
+        Command::none()
+    }
+}
+
+/// Suggests what to do about an error, based on the first cause in its chain
+/// that says what kind of problem it is
+fn error_hint(error: &Error) -> Option<&'static str> {
+    error.chain().find_map(|cause| {
+        if cause.is::<VersionUnavailable>() {
+            Some("Pick another Minecraft or Quilt Loader version.")
+        } else if cause.is::<reqwest::Error>() {
+            Some("Check your internet connection and proxy settings, then try again.")
+        } else if cause.is::<std::io::Error>() {
+            Some(
+                "Check that you can write to the install location, \
+                and close the Minecraft launcher if it is open.",
+            )
+        } else {
+            None
+        }
+    })
+}
+
+/// Parses a `#rrggbb` hex code
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+fn with_tooltip<'a>(
+    content: impl Into<Element<'a, Interaction>>,
+    text: &'static str,
+) -> Element<'a, Interaction> {
+    Tooltip::new(content, text, tooltip::Position::Bottom)
+        .style(theme::Container::Box)
+        .into()
+}
@@ -0,0 +1,37 @@
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use png::Transformations;
+
+/// The bundled icon, decoded to raw pixels
+pub struct DecodedIcon {
+    pub rgba: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Decodes the bundled icon the first time it's needed, or `None` if it isn't a valid PNG
+pub fn decoded() -> Option<&'static DecodedIcon> {
+    static DECODED: OnceLock<Option<DecodedIcon>> = OnceLock::new();
+    DECODED
+        .get_or_init(|| {
+            decode()
+                .map_err(|error| eprintln!("The bundled icon is invalid: {error:?}"))
+                .ok()
+        })
+        .as_ref()
+}
+
+fn decode() -> Result<DecodedIcon> {
+    let mut decoder = png::Decoder::new(crate::ICON);
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+    Ok(DecodedIcon {
+        rgba: buffer,
+        width: info.width,
+        height: info.height,
+    })
+}
@@ -1,31 +1,194 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
+    ffi::OsStr,
     fs::{self, File},
-    io::{Seek, Write},
-    path::PathBuf,
+    future::Future,
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use chrono::{DateTime, Utc};
-use reqwest::Client;
+use reqwest::{
+    header::{CONTENT_TYPE, LAST_MODIFIED},
+    redirect, Client, RequestBuilder, Response, StatusCode,
+};
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+use crate::lockfile::LockedJson;
+use crate::{bundle, cache, icon, settings, volume};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Installation {
     #[default]
     Client,
     Server,
 }
 
+/// The profile types the launcher accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, derive_more::Display, clap::ValueEnum)]
+pub enum ProfileType {
+    #[default]
+    #[display(fmt = "custom")]
+    Custom,
+    #[display(fmt = "latest-release")]
+    LatestRelease,
+    #[display(fmt = "latest-snapshot")]
+    LatestSnapshot,
+}
+
+impl ProfileType {
+    pub const ALL: &[ProfileType] = &[Self::Custom, Self::LatestRelease, Self::LatestSnapshot];
+}
+
+/// What to do when the version being installed is already installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OnConflict {
+    /// Replace the existing install
+    #[default]
+    Overwrite,
+    /// Leave the existing install as it is and succeed without installing
+    Skip,
+    /// Install next to it, with a numeric suffix like `-2` added to the id
+    Rename,
+}
+
+/// How the launch json is laid out when it's written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonStyle {
+    /// Exactly as meta sent it
+    #[default]
+    AsReceived,
+    /// Indented, for reading
+    Pretty,
+    /// Without any whitespace
+    Compact,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClientInstallation {
     pub minecraft_version: MinecraftVersion,
     pub loader_version: LoaderVersion,
     pub install_dir: PathBuf,
+    pub versions_dir: Option<PathBuf>,
+    pub profiles_file: Option<PathBuf>,
+    /// Appended to the version id and profile name, to keep several installs of the same versions
+    pub id_suffix: Option<String>,
+    /// Only remove the files a previous install created when reinstalling,
+    /// instead of the whole version directory
+    pub keep_unknown_files: bool,
+    /// Also write an empty `<id>.jar` next to the launch json, for launchers that refuse
+    /// to start versions without one
+    pub placeholder_jar: bool,
+    /// The game directory the launcher profile runs in, instead of the launcher's default
+    pub game_dir: Option<PathBuf>,
+    /// Install Minecraft versions meta has no profile for, with one adapted from
+    /// a supported version, for testing new snapshots before Quilt supports them
+    pub allow_unsupported: bool,
+    /// Maven coordinate prefixes, like `org.ow2.asm:asm`, of libraries to leave out
+    /// of the launch json, for debugging launch issues
+    pub exclude_libraries: Vec<String>,
+    /// Set when `loader_version` is a build from a maven repository rather than one meta lists
+    pub maven_loader: Option<MavenLoader>,
+    pub on_conflict: OnConflict,
     pub generate_profile: bool,
+    pub profile_type: ProfileType,
+    pub offline: bool,
+    pub canonical: bool,
+    pub deterministic: bool,
+    pub json_style: JsonStyle,
+    pub bundle: Option<PathBuf>,
+    pub verbose: bool,
+    /// Print a single line once installed, instead of each thing being done
+    pub brief: bool,
+    pub meta_urls: Vec<String>,
+    /// The Mojang version manifest the Minecraft version's release date is looked up in
+    pub mojang_meta_url: String,
+    /// The hash the profile json from meta must have, when installing from a lockfile
+    pub locked_json: Option<LockedJson>,
+}
+
+/// Where a loader build meta doesn't list comes from, for testing builds before they are released
+#[derive(Debug, Clone)]
+pub struct MavenLoader {
+    /// The maven repository the build is published to
+    pub repository: String,
+    /// The loader version meta lists whose profile is adapted to the build
+    pub base_version: LoaderVersion,
+}
+
+/// The directory version profiles are installed to in a client directory,
+/// `install_dir/versions` unless overridden for launchers with a nonstandard layout
+pub fn resolve_versions_dir(install_dir: &Path, versions_dir: Option<&Path>) -> PathBuf {
+    versions_dir.map_or_else(|| install_dir.join("versions"), Path::to_path_buf)
+}
+
+impl ClientInstallation {
+    /// [`resolve_versions_dir`] for this installation's directories
+    pub fn resolve_versions_dir(&self) -> PathBuf {
+        resolve_versions_dir(&self.install_dir, self.versions_dir.as_deref())
+    }
+
+    /// The launcher_profiles.json the profile is added to, the one in `install_dir` unless
+    /// another one was picked, which may be outside of it
+    pub fn resolve_profiles_file(&self) -> PathBuf {
+        self.profiles_file
+            .clone()
+            .unwrap_or_else(|| self.install_dir.join("launcher_profiles.json"))
+    }
+
+    /// The version id of the installed profile, including the id suffix
+    pub fn profile_id(&self) -> String {
+        let id = profile_id(&self.loader_version, &self.minecraft_version);
+        match &self.id_suffix {
+            Some(suffix) => format!("{id}-{suffix}"),
+            None => id,
+        }
+    }
+
+    fn verify_install_dir(&self) -> Result<()> {
+        if let Some(game_dir) = &self.game_dir {
+            // The launcher doesn't say what a relative game directory would be relative to
+            if !game_dir.is_absolute() {
+                bail!(
+                    "The game directory {} must be an absolute path",
+                    game_dir.display()
+                );
+            }
+            if game_dir.exists() && !game_dir.is_dir() {
+                bail!("{} is not a directory", game_dir.display());
+            }
+        }
+        match &self.profiles_file {
+            Some(_) if self.install_dir.exists() && !self.install_dir.is_dir() => {
+                bail!("{} is not a directory", self.install_dir.display())
+            }
+            Some(file) if !file.is_file() => bail!("{} does not exist", file.display()),
+            Some(_) => Ok(()),
+            None => verify_client_directory(&self.install_dir),
+        }
+    }
+}
+
+/// Checks that a client can be installed to a launcher directory
+pub fn verify_client_directory(install_dir: &Path) -> Result<()> {
+    if install_dir.exists() && !install_dir.is_dir() {
+        bail!("{} is not a directory", install_dir.display());
+    }
+    if !install_dir.join("launcher_profiles.json").exists() {
+        return Err(missing_launcher_profiles_error(install_dir));
+    }
+    Ok(())
 }
 
 impl std::fmt::Display for ClientInstallation {
@@ -50,18 +213,69 @@ pub struct ServerInstallation {
     pub minecraft_version: MinecraftVersion,
     pub loader_version: LoaderVersion,
     pub install_dir: PathBuf,
+    pub output_dir: Option<PathBuf>,
     pub download_jar: bool,
     pub generate_script: bool,
+    /// Also write the launch scripts only used on other platforms than this one,
+    /// for servers that are set up here but run elsewhere
+    pub all_scripts: bool,
+    /// Download the vanilla server jar even if the one already there is the right one
+    pub force_download: bool,
+    /// Keep the libraries in the shared cache, linking them into the install from there
+    /// instead of downloading them again for every install
+    pub shared_cache: bool,
+    pub java_path: Option<PathBuf>,
+    pub launch_jar_name: Option<String>,
+    pub mojang_meta_url: String,
+    pub systemd_service: bool,
+    /// The account the systemd service runs the server as. A placeholder to fill in is
+    /// written if not set, so the server never runs as root by accident
+    pub systemd_user: Option<String>,
+    pub bundle: Option<PathBuf>,
+    pub meta_urls: Vec<String>,
+    /// Whether to write a readme explaining how to run the server,
+    /// by default only into directories that were empty
+    pub write_readme: Option<bool>,
+    /// Print a single line once installed, instead of each thing being done
+    pub brief: bool,
+    /// The hash the server json from meta must have, when installing from a lockfile
+    pub locked_json: Option<LockedJson>,
+}
+
+impl ServerInstallation {
+    /// The directory downloaded artifacts are written to, `install_dir` unless they are being
+    /// staged somewhere else
+    pub fn resolve_output_dir(&self) -> PathBuf {
+        self.output_dir
+            .clone()
+            .unwrap_or_else(|| self.install_dir.clone())
+    }
+}
+
+impl std::fmt::Display for ServerInstallation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Quilt Loader {} for Minecraft {} to {}",
+            self.loader_version,
+            self.minecraft_version,
+            self.install_dir.display(),
+        )
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, derive_more::Display)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, derive_more::Display)]
 #[display(fmt = "{}", version)]
 pub struct MinecraftVersion {
     pub version: String,
     pub stable: bool,
+    /// Whether meta recommends the version, like a long-term support release.
+    /// Meta doesn't mark any version yet, so this is always false for now
+    #[serde(default)]
+    pub recommended: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, derive_more::Display)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, derive_more::Display)]
 #[display(fmt = "{}", version)]
 pub struct LoaderVersion {
     pub separator: char,
@@ -70,22 +284,365 @@ pub struct LoaderVersion {
     pub version: Version,
 }
 
-pub async fn fetch_minecraft_versions(client: Client) -> Result<Vec<MinecraftVersion>> {
-    Ok(client
-        .get("https://meta.quiltmc.org/v3/versions/game")
-        .send()
-        .await?
-        .json()
-        .await?)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, derive_more::Display)]
+pub enum InstallStep {
+    #[display(fmt = "Downloading profile json")]
+    DownloadProfileJson,
+    #[display(fmt = "Downloading libraries")]
+    DownloadLibraries,
+    #[display(fmt = "Downloading server jar")]
+    DownloadServerJar,
+    #[display(fmt = "Updating launcher profiles")]
+    UpdateLauncherProfiles,
 }
 
-pub async fn fetch_loader_versions(client: Client) -> Result<Vec<LoaderVersion>> {
-    Ok(client
-        .get("https://meta.quiltmc.org/v3/versions/loader")
-        .send()
-        .await?
-        .json()
-        .await?)
+/// Structured progress events reported by [`install_client`] and [`install_server`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InstallProgress {
+    /// A step started, and there is no telling how long it will take until it reports
+    /// a [`Determinate`](Self::Determinate) progress, if ever
+    Indeterminate(InstallStep),
+    /// A step is running, and this fraction of it, from 0 to 1, is done
+    Determinate(InstallStep, f32),
+    StepCompleted(InstallStep),
+}
+
+/// An optional callback receiving [`InstallProgress`] events
+pub type ProgressCallback<'a> = Option<&'a (dyn Fn(InstallProgress) + Sync)>;
+
+fn report(progress: ProgressCallback<'_>, event: InstallProgress) {
+    if let Some(progress) = progress {
+        progress(event);
+    }
+}
+
+const MINECRAFT_VERSIONS_CACHE: &str = "minecraft-versions.json";
+const LOADER_VERSIONS_CACHE: &str = "loader-versions.json";
+
+pub const QUILT_META_URL: &str = "https://meta.quiltmc.org";
+
+/// Sends a request to each meta mirror in turn until one of them answers.
+/// A "not found" is an answer, since the other mirrors would say the same
+async fn with_meta_mirrors<T, F, Fut>(meta_urls: &[String], mut request: F) -> Result<T>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut last_error = anyhow!("No Quilt meta URL is configured");
+    for (i, meta_url) in meta_urls.iter().enumerate() {
+        match request(meta_url.trim_end_matches('/').to_owned()).await {
+            Err(error) if !is_not_found(&error) => {
+                if i + 1 < meta_urls.len() {
+                    eprintln!("Quilt meta at {meta_url} failed, trying the next mirror: {error:#}");
+                }
+                last_error = error;
+            }
+            result => return result,
+        }
+    }
+    Err(last_error)
+}
+
+pub async fn fetch_minecraft_versions(
+    client: Client,
+    meta_urls: &[String],
+) -> Result<Vec<MinecraftVersion>> {
+    let client = &client;
+    let response = with_meta_mirrors(meta_urls, |meta_url| async move {
+        let response = send(client.get(format!("{meta_url}/v3/versions/game"))).await?;
+        Ok(check_meta_response(response)?.text().await?)
+    })
+    .await?;
+    let versions = serde_json::from_str(&response)?;
+    cache::write(MINECRAFT_VERSIONS_CACHE, &response);
+    Ok(versions)
+}
+
+pub async fn fetch_loader_versions(
+    client: Client,
+    meta_urls: &[String],
+) -> Result<Vec<LoaderVersion>> {
+    let client = &client;
+    let response = with_meta_mirrors(meta_urls, |meta_url| async move {
+        let response = send(client.get(format!("{meta_url}/v3/versions/loader"))).await?;
+        Ok(check_meta_response(response)?.text().await?)
+    })
+    .await?;
+    let versions = serde_json::from_str(&response)?;
+    cache::write(LOADER_VERSIONS_CACHE, &response);
+    Ok(only_quilt_loader(versions))
+}
+
+/// Skips entries that aren't Quilt Loader, which a compromised or misconfigured mirror could
+/// use to point installs at another artifact
+fn only_quilt_loader(versions: Vec<LoaderVersion>) -> Vec<LoaderVersion> {
+    versions
+        .into_iter()
+        .filter(|version| {
+            let valid = version.maven.starts_with("org.quiltmc:quilt-loader:");
+            if !valid {
+                eprintln!(
+                    "Skipping Quilt Loader version {version} with unexpected maven coordinates {}",
+                    version.maven
+                );
+            }
+            valid
+        })
+        .collect()
+}
+
+/// The Minecraft versions from the last successful [`fetch_minecraft_versions`]
+pub fn cached_minecraft_versions() -> Result<Vec<MinecraftVersion>> {
+    let response = cache::read(MINECRAFT_VERSIONS_CACHE)
+        .context("No Minecraft versions are cached, run the installer online once first")?;
+    Ok(serde_json::from_str(&response)?)
+}
+
+/// The loader versions from the last successful [`fetch_loader_versions`]
+pub fn cached_loader_versions() -> Result<Vec<LoaderVersion>> {
+    let response = cache::read(LOADER_VERSIONS_CACHE)
+        .context("No Quilt Loader versions are cached, run the installer online once first")?;
+    Ok(only_quilt_loader(serde_json::from_str(&response)?))
+}
+
+/// Fetches the loader versions that can be installed for a specific Minecraft version
+pub async fn fetch_loader_versions_for(
+    client: &Client,
+    meta_urls: &[String],
+    minecraft_version: &MinecraftVersion,
+) -> Result<Vec<LoaderVersion>> {
+    #[derive(Deserialize)]
+    struct Entry {
+        loader: LoaderVersion,
+    }
+
+    let entries: Vec<Entry> = with_meta_mirrors(meta_urls, |meta_url| async move {
+        let response = send(client.get(format!(
+            "{meta_url}/v3/versions/loader/{}",
+            minecraft_version.version
+        )))
+        .await?;
+        Ok(check_meta_response(response)?.json().await?)
+    })
+    .await?;
+    Ok(only_quilt_loader(
+        entries.into_iter().map(|entry| entry.loader).collect(),
+    ))
+}
+
+static TRACE_REQUESTS: AtomicBool = AtomicBool::new(false);
+
+/// Logs every request to stderr from now on, for debugging which mirror or URL was used
+pub fn enable_request_tracing() {
+    TRACE_REQUESTS.store(true, Ordering::Relaxed);
+}
+
+/// Sends a request, retrying it after transient failures
+async fn send(mut request: RequestBuilder) -> reqwest::Result<Response> {
+    let retries = RETRIES.get().copied().unwrap_or(DEFAULT_RETRIES);
+    let mut attempt = 0;
+    loop {
+        // A request whose body can't be cloned can only be sent once
+        let retry =
+            retry_delay(attempt, retries).and_then(|delay| Some((request.try_clone()?, delay)));
+        let response = send_once(request).await;
+        let Some((next, delay)) = retry.filter(|_| is_transient_failure(&response)) else {
+            return response;
+        };
+        let reason = match &response {
+            Ok(response) => response.status().to_string(),
+            Err(error) => error.to_string(),
+        };
+        eprintln!(
+            "Warning: request failed ({reason}), retrying in {:.1}s",
+            delay.as_secs_f64()
+        );
+        tokio::time::sleep(delay).await;
+        request = next;
+        attempt += 1;
+    }
+}
+
+/// The number of times a failed request is retried unless set otherwise
+pub const DEFAULT_RETRIES: u32 = 3;
+/// How long to wait before the first retry, doubling before each further one
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+static RETRIES: OnceLock<u32> = OnceLock::new();
+
+/// Retries requests that fail in a way that may pass, like a dropped connection or
+/// a busy server, up to `retries` times. Only the first call has an effect
+pub fn set_retries(retries: u32) {
+    let _ = RETRIES.set(retries);
+}
+
+/// How long to wait before retrying after the given number of retries, or `None` once
+/// they are used up
+fn retry_delay(attempt: u32, retries: u32) -> Option<Duration> {
+    (attempt < retries).then(|| RETRY_BACKOFF * 2u32.pow(attempt.min(6)))
+}
+
+fn is_transient_failure(response: &reqwest::Result<Response>) -> bool {
+    match response {
+        Ok(response) => is_transient_status(response.status()),
+        Err(error) => is_transient_error(error),
+    }
+}
+
+/// A busy or failing server may recover, a client error won't
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Only connecting and timing out are worth another try, other errors like an invalid URL,
+/// a TLS failure or a broken body would fail the same way again
+fn is_transient_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout()
+}
+
+/// Sends a request once, and logs its final URL after redirects, status and size if tracing is on.
+/// The size is the server's Content-Length, downloads also log how much they received
+async fn send_once(request: RequestBuilder) -> reqwest::Result<Response> {
+    let response = request.send().await;
+    if TRACE_REQUESTS.load(Ordering::Relaxed) {
+        match &response {
+            Ok(response) => eprintln!(
+                "[request] GET {} -> {} ({})",
+                response.url(),
+                response.status(),
+                response
+                    .content_length()
+                    .map_or("unknown size".into(), |length| format!("{length} bytes"))
+            ),
+            Err(error) => match error.url() {
+                Some(url) => eprintln!("[request] GET {url} -> {error}"),
+                None => eprintln!("[request] GET -> {error}"),
+            },
+        }
+    }
+    response
+}
+
+/// The most redirects followed for one request, the same as reqwest's default
+const MAX_REDIRECTS: usize = 10;
+
+const CAPTIVE_PORTAL_HINT: &str = "You may be behind a captive portal, like the sign-in page \
+    of a public Wi-Fi network. Open a website in your browser to sign in, then try again";
+
+/// Follows redirects, but stops at loops, long chains and redirects from HTTPS to plain HTTP.
+/// Quilt's and Mojang's servers never send the latter, while captive portals do
+pub fn redirect_policy() -> redirect::Policy {
+    redirect::Policy::custom(|attempt| {
+        if attempt.previous().contains(attempt.url()) {
+            let error = format!("{} redirects back to itself", attempt.url());
+            attempt.error(error)
+        } else if attempt.previous().len() >= MAX_REDIRECTS {
+            let error = format!(
+                "Gave up after {MAX_REDIRECTS} redirects at {}",
+                attempt.url()
+            );
+            attempt.error(error)
+        } else if attempt.url().scheme() == "http"
+            && attempt.previous().iter().any(|url| url.scheme() == "https")
+        {
+            let error = format!(
+                "Redirected from HTTPS to {}. {CAPTIVE_PORTAL_HINT}",
+                attempt.url()
+            );
+            attempt.error(error)
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+fn has_content_type(response: &Response, expected: &str) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map_or(false, |content_type| content_type.contains(expected))
+}
+
+/// Fails on an error status, then catches meta serving something other than json, like
+/// a captive portal's sign-in page, which would otherwise fail parsing with a cryptic error.
+/// The status comes first, so a 404 stays recognizable as one whatever its body is
+fn check_meta_response(response: Response) -> Result<Response> {
+    let response = response.error_for_status()?;
+    if !has_content_type(&response, "json") {
+        if has_content_type(&response, "html") {
+            bail!(
+                "Quilt meta returned a web page instead of version data (HTTP {}) from {}. \
+                {CAPTIVE_PORTAL_HINT}",
+                response.status(),
+                response.url()
+            );
+        }
+        bail!(
+            "Quilt meta returned an unexpected response instead of version data from {}",
+            response.url()
+        );
+    }
+    Ok(response)
+}
+
+fn is_not_found(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        == Some(StatusCode::NOT_FOUND)
+}
+
+/// The error for versions meta doesn't have, told apart from network and filesystem errors
+/// so the GUI can suggest picking other versions
+#[derive(Debug, derive_more::Display)]
+pub struct VersionUnavailable(String);
+
+impl std::error::Error for VersionUnavailable {}
+
+/// Builds the error for a loader/Minecraft combination meta doesn't know about,
+/// suggesting the newest loader that does support the Minecraft version
+async fn unsupported_combination_error(
+    client: &Client,
+    meta_urls: &[String],
+    minecraft_version: &MinecraftVersion,
+    loader_version: &LoaderVersion,
+) -> anyhow::Error {
+    let versions = fetch_loader_versions_for(client, meta_urls, minecraft_version)
+        .await
+        .unwrap_or_default();
+    let suggestion = versions
+        .iter()
+        .find(|v| v.version.pre.is_empty())
+        .or(versions.first());
+
+    let message = match suggestion {
+        Some(suggestion) => format!(
+            "Quilt Loader {loader_version} is not available for Minecraft {minecraft_version}. \
+            Try Quilt Loader {suggestion}, the newest version available for it"
+        ),
+        None => format!("No Quilt Loader version is available for Minecraft {minecraft_version}"),
+    };
+    VersionUnavailable(message).into()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct InstallerRelease {
+    pub tag_name: String,
+    pub html_url: String,
+}
+
+/// Checks GitHub for a release of the installer newer than the running one
+pub async fn fetch_newer_installer_release(client: Client) -> Result<Option<InstallerRelease>> {
+    let release: InstallerRelease = send(
+        client.get("https://api.github.com/repos/QuiltMC/quilt-native-installer/releases/latest"),
+    )
+    .await?
+    .error_for_status()?
+    .json()
+    .await?;
+    let latest = Version::parse(release.tag_name.trim_start_matches('v'))?;
+    let current = Version::parse(env!("CARGO_PKG_VERSION"))?;
+    Ok((latest > current).then_some(release))
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -127,27 +684,225 @@ pub fn get_default_client_directory() -> PathBuf {
     PathBuf::from(std::env::var("HOME").unwrap()).join(".minecraft")
 }
 
-pub async fn install_client(client: Client, args: ClientInstallation) -> Result<()> {
-    println!("Installing client {args}");
+#[cfg(target_os = "windows")]
+fn get_alternative_client_directories() -> Vec<PathBuf> {
+    // The Microsoft Store launcher is sandboxed into its package directory
+    std::env::var("LOCALAPPDATA")
+        .map(|local| {
+            vec![PathBuf::from(local)
+                .join("Packages")
+                .join("Microsoft.4297127D64EC6_8wekyb3d8bbwe")
+                .join("LocalCache")
+                .join("Roaming")
+                .join(".minecraft")]
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "macos")]
+fn get_alternative_client_directories() -> Vec<PathBuf> {
+    Vec::new()
+}
 
-    // Verify install location
-    if !args.install_dir.join("launcher_profiles.json").exists() {
-        bail!(
-            "{} is not a valid installation directory",
-            args.install_dir.display(),
+#[cfg(target_os = "linux")]
+fn get_alternative_client_directories() -> Vec<PathBuf> {
+    // Flatpak
+    vec![PathBuf::from(std::env::var("HOME").unwrap())
+        .join(".var")
+        .join("app")
+        .join("com.mojang.Minecraft")
+        .join(".minecraft")]
+}
+
+/// Finds the launcher directories on this machine that contain a `launcher_profiles.json`,
+/// starting with the default location
+pub fn detect_client_directories() -> Vec<PathBuf> {
+    std::iter::once(get_default_client_directory())
+        .chain(get_alternative_client_directories())
+        .filter(|dir| dir.join("launcher_profiles.json").is_file())
+        .collect()
+}
+
+/// Finds the launcher profile files in the detected launcher directories.
+/// Some directories hold several for parallel launcher configurations,
+/// like the Microsoft Store launcher's `launcher_profiles_microsoft_store.json`
+pub fn detect_launcher_profiles() -> Vec<PathBuf> {
+    detect_client_directories()
+        .iter()
+        .flat_map(|dir| launcher_profiles_in(dir))
+        .collect()
+}
+
+/// The launcher profile files in one launcher directory, sorted by name
+fn launcher_profiles_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            (name.starts_with("launcher_profiles") && name.ends_with(".json")).then_some(path)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Explains why a directory without launcher_profiles.json can't be installed to,
+/// recognizing the files newer launchers keep instead
+fn missing_launcher_profiles_error(install_dir: &Path) -> anyhow::Error {
+    if let Some(file) = launcher_profiles_in(install_dir).first() {
+        return anyhow!(
+            "{} has no launcher_profiles.json, but this launcher keeps its profiles in {}. \
+            Select that file as the profiles file in the GUI to install to it",
+            install_dir.display(),
+            file.display()
+        );
+    }
+    // Newer launchers write these on their first start, before any profile
+    if ["launcher_settings.json", "launcher_accounts.json"]
+        .iter()
+        .any(|file| install_dir.join(file).exists())
+    {
+        return anyhow!(
+            "The launcher in {} has not created launcher_profiles.json yet. \
+            Start the launcher once, then install again",
+            install_dir.display()
         );
     }
+    anyhow!(
+        "{} is not a valid installation directory",
+        install_dir.display()
+    )
+}
+
+/// The detected launcher directory, falling back to the platform default if none was found
+pub fn detect_client_directory() -> PathBuf {
+    let detected = detect_client_directories();
+    if let Some(remembered) = settings::load().client_directory {
+        // Forgotten once the launcher there is uninstalled or moved
+        if detected.contains(&remembered) {
+            return remembered;
+        }
+    }
+    detected
+        .into_iter()
+        .next()
+        .unwrap_or_else(get_default_client_directory)
+}
+
+/// Remembers which launcher directory was installed to, if there were several to choose from.
+/// Failing to is only logged, since the install itself succeeded
+fn remember_client_directory(install_dir: &Path) {
+    let detected = detect_client_directories();
+    if detected.len() < 2 || !detected.iter().any(|dir| dir == install_dir) {
+        return;
+    }
+    if settings::load().client_directory.as_deref() == Some(install_dir) {
+        return;
+    }
+    if let Err(error) = settings::update("client_directory", install_dir) {
+        eprintln!("Failed to remember the launcher directory: {error:#}");
+    }
+}
+
+/// The version id of the profile, which also names its directory in `versions`
+pub fn profile_id(loader_version: &LoaderVersion, minecraft_version: &MinecraftVersion) -> String {
+    format!("quilt-loader-{loader_version}-{minecraft_version}")
+}
+
+/// The name the launcher shows for the profile
+pub fn profile_display_name(minecraft_version: &MinecraftVersion) -> String {
+    format!("Quilt Loader {}", minecraft_version.version)
+}
+
+pub async fn install_client(
+    client: Client,
+    mut args: ClientInstallation,
+    progress: ProgressCallback<'_>,
+) -> Result<()> {
+    if !args.brief {
+        println!("Installing client {args}");
+    }
+
+    // Verify install location
+    args.verify_install_dir()?;
+    if let Some(warning) = volume::install_dir_warning(&args.install_dir) {
+        eprintln!("Warning: {warning}");
+    }
+
+    // Handle an existing install of the same version
+    let installed_id = args.profile_id();
+    if args.resolve_versions_dir().join(&installed_id).exists() {
+        match args.on_conflict {
+            OnConflict::Overwrite => {}
+            OnConflict::Skip => {
+                if args.brief {
+                    println!("skipped {installed_id} -> {}", args.install_dir.display());
+                } else {
+                    println!("{installed_id} is already installed, leaving it as it is.");
+                }
+                return Ok(());
+            }
+            OnConflict::Rename => {
+                let base_id = profile_id(&args.loader_version, &args.minecraft_version);
+                let base_suffix = args.id_suffix.take();
+                args.id_suffix = (2..)
+                    .map(|n| match &base_suffix {
+                        Some(suffix) => format!("{suffix}-{n}"),
+                        None => n.to_string(),
+                    })
+                    .find(|suffix| {
+                        !args
+                            .resolve_versions_dir()
+                            .join(format!("{base_id}-{suffix}"))
+                            .exists()
+                    });
+                if !args.brief {
+                    println!(
+                        "{installed_id} is already installed, installing as {}",
+                        args.profile_id()
+                    );
+                }
+            }
+        }
+    }
+
+    if !args.offline && args.bundle.is_none() {
+        if let Ok(manifest) = fetch_version_manifest_briefly(&client, &args.mojang_meta_url).await {
+            let repository = args
+                .maven_loader
+                .as_ref()
+                .map_or(QUILT_MAVEN_URL, |maven_loader| &maven_loader.repository);
+            warn_if_loader_predates(
+                &client,
+                &manifest,
+                repository,
+                &args.minecraft_version,
+                &args.loader_version,
+            )
+            .await;
+        }
+    }
+
+    // Download launch json
+    let response = fetch_profile_json(&client, &args, progress).await?;
 
     // Resolve profile directory
-    let profile_name = format!(
-        "quilt-loader-{}-{}",
-        args.loader_version, args.minecraft_version
-    );
-    let profile_dir = args.install_dir.join("versions").join(&profile_name);
+    let profile_name = args.profile_id();
+    let profile_dir = extended_length_path(&args.resolve_versions_dir()).join(&profile_name);
 
     // Delete existing profile
     if profile_dir.exists() {
-        fs::remove_dir_all(&profile_dir)?;
+        if is_incomplete_install(&profile_dir, &profile_name) && !args.brief {
+            println!("Cleaning up an incomplete previous installation of {profile_name}");
+        }
+        if args.keep_unknown_files {
+            remove_installed_files(&profile_dir, &profile_name)?;
+        } else {
+            fs::remove_dir_all(&profile_dir)?;
+        }
     }
 
     // Create profile directory
@@ -156,76 +911,1740 @@ pub async fn install_client(client: Client, args: ClientInstallation) -> Result<
     // Create launch json
     let mut file = File::create(profile_dir.join(profile_name.clone() + ".json"))?;
 
-    // Download launch json
-    let mut response = client
-        .get(format!(
-            "https://meta.quiltmc.org/v3/versions/loader/{}/{}/profile/json",
-            &args.minecraft_version.version, &args.loader_version.version
-        ))
-        .send()
-        .await?
-        .text()
-        .await?;
+    let response = patch_profile_json(&response, &args, &profile_name)?;
+    let response = match args.json_style {
+        JsonStyle::AsReceived => response,
+        JsonStyle::Pretty => reformat_json(&response, true),
+        JsonStyle::Compact => reformat_json(&response, false),
+    };
+
+    file.write_all(response.as_bytes())?;
+    let mut installed_files = vec![format!("{profile_name}.json")];
+
+    // The vanilla launcher takes the game jar from the inherited version,
+    // but some third party launchers expect every version to have its own
+    if args.placeholder_jar {
+        let jar_name = format!("{profile_name}.jar");
+        zip::ZipWriter::new(File::create(profile_dir.join(&jar_name))?).finish()?;
+        installed_files.push(jar_name);
+    }
+
+    fs::write(
+        profile_dir.join(INSTALLED_FILES),
+        serde_json::to_string_pretty(&installed_files)?,
+    )?;
+
+    // Copy libraries, which the launcher can't download without network access
+    if let Some(bundle) = &args.bundle {
+        let libraries_dir = extended_length_path(&args.install_dir.join("libraries"));
+        for (_, path) in library_downloads(&response)? {
+            // Extended-length paths are taken literally, so the maven path's slashes are split up
+            let destination = path
+                .split('/')
+                .fold(libraries_dir.clone(), |dir, part| dir.join(part));
+            bundle::copy_library(bundle, &path, &destination)?;
+        }
+    }
+
+    // Generate profile
+    if args.generate_profile {
+        write_launcher_profile(&args, profile_name, progress)?;
+    }
+    remember_client_directory(&args.install_dir);
+
+    if args.brief {
+        let profile_id = args.profile_id();
+        println!("installed {profile_id} -> {}", args.install_dir.display());
+    } else {
+        println!("Client installed successfully.");
+    }
+    Ok(())
+}
+
+/// Applies the fixes and options that change the launch json from meta before it's written
+fn patch_profile_json(
+    response: &str,
+    args: &ClientInstallation,
+    profile_name: &str,
+) -> Result<String> {
+    let hashed_fix = args.loader_version.version < Version::new(0, 17, 7);
+    if !hashed_fix && args.exclude_libraries.is_empty() && args.id_suffix.is_none() {
+        // Written as meta sent it
+        return Ok(response.to_owned());
+    }
+
+    let mut json: Value = serde_json::from_str(response)?;
+    let libs = json
+        .as_object_mut()
+        .and_then(|json| json.get_mut("libraries"))
+        .and_then(Value::as_array_mut)
+        .context("launch json has no libraries array")?;
+    let library_name = |lib: &Value| lib["name"].as_str().unwrap_or_default().to_owned();
 
     // Hack-Fix:
     // Was fixed in versions above 0.17.7
-    if args.loader_version.version < Version::new(0, 17, 7) {
+    if hashed_fix {
         // Quilt-meta specifies both hashed and intermediary,
         // but providing both to quilt-loader causes it to silently fail remapping.
-        let mut json: Value = serde_json::from_str(&response)?;
-        let libs = json
-            .as_object_mut()
-            .unwrap()
-            .get_mut("libraries")
-            .unwrap()
-            .as_array_mut()
-            .unwrap();
+        libs.retain(|lib| !library_name(lib).starts_with("org.quiltmc:hashed"));
+    }
+    // End of hack-fix
+
+    for prefix in &args.exclude_libraries {
+        let count = libs.len();
         libs.retain(|lib| {
-            !lib.as_object()
-                .unwrap()
-                .get("name")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .starts_with("org.quiltmc:hashed")
+            let name = library_name(lib);
+            let excluded = name.starts_with(prefix.as_str());
+            if excluded && !args.brief {
+                println!("Excluding library {name}");
+            }
+            !excluded
         });
-        response = serde_json::to_string(&json)?;
+        if libs.len() == count {
+            eprintln!("Warning: no library matches {prefix}, so none was excluded");
+        }
     }
-    // End of hack-fix
 
-    file.write_all(response.as_bytes())?;
+    // The launcher expects the id in the json to match the version directory
+    if args.id_suffix.is_some() {
+        json["id"] = Value::String(profile_name.to_owned());
+    }
 
-    // Generate profile
-    if args.generate_profile {
-        let mut file = fs::OpenOptions::new().read(true).write(true).open(
-            args.install_dir
-                .join("launcher_profiles")
-                .with_extension("json"),
-        )?;
+    Ok(serde_json::to_string(&json)?)
+}
 
-        let mut launcher_profiles: LauncherProfiles = serde_json::from_reader(&file)?;
-        launcher_profiles.profiles.insert(
-            profile_name.clone(),
-            Profile {
-                name: format!("Quilt Loader {}", &args.minecraft_version.version),
-                profile_type: "custom".into(),
-                created: Utc::now(),
-                last_version_id: profile_name,
-                icon: format!("data:image/png;base64,{}", BASE64.encode(crate::ICON)),
-                other: Map::new(),
-            },
+/// Changes only the whitespace between the tokens of valid json, indenting it or removing it,
+/// so the keys keep their order and numbers their formatting
+fn reformat_json(json: &str, pretty: bool) -> String {
+    fn new_line(out: &mut String, depth: usize) {
+        out.push('\n');
+        out.extend(std::iter::repeat("  ").take(depth));
+    }
+
+    let mut out = String::with_capacity(json.len());
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut opened = false;
+    for c in json.chars() {
+        if in_string {
+            out.push(c);
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        if matches!(c, ' ' | '\t' | '\n' | '\r') {
+            continue;
+        }
+
+        // Empty objects and arrays stay on one line
+        let was_opened = std::mem::take(&mut opened);
+        if pretty && was_opened && !matches!(c, '}' | ']') {
+            new_line(&mut out, depth);
+        }
+        match c {
+            '{' | '[' => {
+                depth += 1;
+                opened = true;
+            }
+            '}' | ']' => {
+                depth -= 1;
+                if pretty && !was_opened {
+                    new_line(&mut out, depth);
+                }
+            }
+            '"' => in_string = true,
+            _ => {}
+        }
+        out.push(c);
+        if pretty {
+            match c {
+                ',' => new_line(&mut out, depth),
+                ':' => out.push(' '),
+                _ => {}
+            }
+        }
+    }
+    out
+}
+
+/// Adds the launcher profile for an installation whose version files are already in place,
+/// for when only the profile was deleted
+pub fn refresh_launcher_profile(args: ClientInstallation) -> Result<()> {
+    args.verify_install_dir()?;
+    let profile_name = args.profile_id();
+    let profile_dir = args.resolve_versions_dir().join(&profile_name);
+    if !profile_dir.is_dir() {
+        bail!(
+            "{} does not exist, install the client first",
+            profile_dir.display()
+        );
+    }
+
+    write_launcher_profile(&args, profile_name.clone(), None)?;
+    if args.brief {
+        println!(
+            "refreshed {profile_name} -> {}",
+            args.resolve_profiles_file().display()
+        );
+    } else {
+        println!("Launcher profile refreshed successfully.");
+    }
+    Ok(())
+}
+
+/// Parses launcher_profiles.json, which older launchers on Windows sometimes wrote with a BOM
+/// or in a legacy encoding. Bytes that aren't UTF-8 are replaced with a warning,
+/// since the installer only needs its own profile to be readable
+fn parse_launcher_profiles(file: &[u8], path: &Path) -> Result<LauncherProfiles> {
+    let contents = file.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(file);
+    let bom_len = file.len() - contents.len();
+    let json = match std::str::from_utf8(contents) {
+        Ok(json) => Cow::Borrowed(json),
+        Err(error) => {
+            eprintln!(
+                "Warning: {} is not valid UTF-8 from byte {} on, \
+                replacing the characters that can't be read",
+                path.display(),
+                error.valid_up_to()
+            );
+            String::from_utf8_lossy(contents)
+        }
+    };
+    serde_json::from_str(&json).map_err(|error| {
+        // serde_json counts lines and columns, byte offsets are easier to find in a hex editor
+        let decoded_offset: usize = json
+            .split_inclusive('\n')
+            .take(error.line().saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>()
+            + error.column().saturating_sub(1);
+        let offset = bom_len + original_offset(contents, decoded_offset);
+        anyhow!(
+            "{} is not a valid launcher profiles file at byte {offset}: {error}",
+            path.display()
+        )
+    })
+}
+
+/// Maps a byte offset in the lossily decoded text back to the bytes it was decoded from,
+/// where each replacement character stands for the invalid bytes it replaced
+fn original_offset(contents: &[u8], decoded_offset: usize) -> usize {
+    const REPLACEMENT_LEN: usize = char::REPLACEMENT_CHARACTER.len_utf8();
+    let (mut original, mut decoded) = (0, 0);
+    let mut rest = contents;
+    loop {
+        let (valid, invalid) = match std::str::from_utf8(rest) {
+            Ok(valid) => (valid.len(), None),
+            Err(error) => (
+                error.valid_up_to(),
+                Some(
+                    error
+                        .error_len()
+                        .unwrap_or(rest.len() - error.valid_up_to()),
+                ),
+            ),
+        };
+        if decoded_offset <= decoded + valid {
+            return original + decoded_offset - decoded;
+        }
+        decoded += valid;
+        original += valid;
+        let Some(invalid) = invalid else {
+            return original;
+        };
+        if decoded_offset < decoded + REPLACEMENT_LEN {
+            return original;
+        }
+        decoded += REPLACEMENT_LEN;
+        original += invalid;
+        rest = &rest[valid + invalid..];
+    }
+}
+
+/// Keeps a copy of a launcher_profiles.json that isn't UTF-8 before it's rewritten, as the
+/// characters that couldn't be read are written back as replacement characters
+fn back_up_unreadable_profiles(contents: &[u8], path: &Path) -> Result<()> {
+    if std::str::from_utf8(contents).is_ok() {
+        return Ok(());
+    }
+    let backup = path.with_extension("json.non-utf8.bak");
+    fs::write(&backup, contents)
+        .with_context(|| format!("Could not back up {} before updating it", path.display()))?;
+    eprintln!(
+        "Warning: updating {} replaces the characters that can't be read for good, \
+        the original is kept as {}",
+        path.display(),
+        backup.display()
+    );
+    Ok(())
+}
+
+/// Explains the sharing and lock violations Windows reports while another program,
+/// usually the running launcher, has launcher_profiles.json open
+fn profiles_file_error(error: std::io::Error, path: &Path) -> anyhow::Error {
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+    if cfg!(target_os = "windows")
+        && matches!(
+            error.raw_os_error(),
+            Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)
+        )
+    {
+        return anyhow!(
+            "{} is in use by another program, most likely the Minecraft launcher. \
+            Close the launcher, then try again",
+            path.display()
         );
+    }
+    anyhow::Error::new(error).context(format!("Failed to update {}", path.display()))
+}
+
+/// Adds or replaces the installation's profile in launcher_profiles.json
+fn write_launcher_profile(
+    args: &ClientInstallation,
+    profile_name: String,
+    progress: ProgressCallback<'_>,
+) -> Result<()> {
+    report(
+        progress,
+        InstallProgress::Indeterminate(InstallStep::UpdateLauncherProfiles),
+    );
+    let path = extended_length_path(&args.resolve_profiles_file());
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)
+        .map_err(|error| profiles_file_error(error, &path))?;
 
-        file.set_len(0)?;
-        file.rewind()?;
-        serde_json::to_writer_pretty(file, &launcher_profiles)?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(|error| profiles_file_error(error, &path))?;
+    let mut launcher_profiles = parse_launcher_profiles(&contents, &path)?;
+    back_up_unreadable_profiles(&contents, &path)?;
+    match launcher_profiles.profiles.get_mut(&profile_name) {
+        // A reinstall of the same version only updates the profile in place, so the user's
+        // changes to it, like its name, icon, Java arguments or game directory, are kept
+        Some(profile) if profile.last_version_id == profile_name => {
+            profile.profile_type = args.profile_type.to_string();
+            if let Some(game_dir) = &args.game_dir {
+                profile
+                    .other
+                    .insert("gameDir".into(), game_dir.display().to_string().into());
+            }
+        }
+        existing => {
+            let created = if args.deterministic {
+                // Keep the timestamp of the profile being replaced,
+                // so reinstalls don't change the file
+                existing.map_or_else(|| SystemTime::UNIX_EPOCH.into(), |profile| profile.created)
+            } else {
+                Utc::now()
+            };
+            launcher_profiles.profiles.insert(
+                profile_name.clone(),
+                Profile {
+                    name: match &args.id_suffix {
+                        Some(suffix) => {
+                            format!("{}-{suffix}", profile_display_name(&args.minecraft_version))
+                        }
+                        None => profile_display_name(&args.minecraft_version),
+                    },
+                    profile_type: args.profile_type.to_string(),
+                    created,
+                    last_version_id: profile_name,
+                    icon: profile_icon(),
+                    other: args
+                        .game_dir
+                        .iter()
+                        .map(|dir| ("gameDir".into(), dir.display().to_string().into()))
+                        .collect(),
+                },
+            );
+        }
     }
 
-    println!("Client installed successfully.");
+    file.set_len(0)
+        .and_then(|_| file.rewind())
+        .map_err(|error| profiles_file_error(error, &path))?;
+    let written = if args.canonical {
+        // Objects in a `Value` are sorted by key, unlike the `HashMap` of profiles
+        serde_json::to_writer_pretty(file, &serde_json::to_value(&launcher_profiles)?)
+    } else {
+        serde_json::to_writer_pretty(file, &launcher_profiles)
+    };
+    written.map_err(|error| profiles_file_error(error.into(), &path))?;
+    report(
+        progress,
+        InstallProgress::StepCompleted(InstallStep::UpdateLauncherProfiles),
+    );
+    if args.verbose {
+        // Reassures that existing profiles were kept
+        println!(
+            "launcher_profiles.json now has {} profiles",
+            launcher_profiles.profiles.len()
+        );
+    }
     Ok(())
 }
 
-pub async fn install_server(_client: Client, args: ServerInstallation) -> Result<()> {
-    println!("Installing server\n{args:#?}");
-    Err(anyhow!("Server installation hasn't been implemented!"))
+/// The bundled icon as a data URI, or one of the launcher's built in icons
+/// if it's corrupt, since the launcher would show a broken image for it
+fn profile_icon() -> String {
+    if icon::decoded().is_some() {
+        format!("data:image/png;base64,{}", BASE64.encode(crate::ICON))
+    } else {
+        "Furnace".into()
+    }
+}
+
+/// Converts a path to the `\\?\` extended-length form on Windows, so paths below it can exceed
+/// the 260 character MAX_PATH limit
+fn extended_length_path(path: &Path) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        // Canonical paths on Windows are in the extended-length form,
+        // but only existing paths can be canonicalized
+        if let Ok(path) = fs::canonicalize(path) {
+            return path;
+        }
+        if let (Some(parent), Some(name)) = (path.parent(), path.file_name()) {
+            if let Ok(parent) = fs::canonicalize(parent) {
+                return parent.join(name);
+            }
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Lists the files in a version directory that the installer created,
+/// so a reinstall can leave the rest alone
+const INSTALLED_FILES: &str = "quilt-installer-files.json";
+
+/// Removes the files a previous install created from a version directory, keeping any others.
+/// The launch json is all that is removed for installs from before the list was written
+fn remove_installed_files(profile_dir: &Path, profile_name: &str) -> Result<()> {
+    let files: Vec<String> = fs::read(profile_dir.join(INSTALLED_FILES))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_else(|| vec![format!("{profile_name}.json")]);
+    for file in files.iter().map(String::as_str).chain([INSTALLED_FILES]) {
+        // Never follow a tampered list outside of the version directory
+        if Path::new(file).file_name() != Some(OsStr::new(file)) {
+            bail!("{INSTALLED_FILES} lists the invalid file name {file}");
+        }
+        match fs::remove_file(profile_dir.join(file)) {
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// Whether a version directory was left behind by an install that was interrupted
+/// before its launch json was completely written
+fn is_incomplete_install(profile_dir: &Path, profile_name: &str) -> bool {
+    fs::read(profile_dir.join(format!("{profile_name}.json")))
+        .map_or(true, |json| serde_json::from_slice::<Value>(&json).is_err())
+}
+
+/// Downloads the launch json for the installation, or reads it from the bundle or,
+/// when offline, the cache
+async fn fetch_profile_json(
+    client: &Client,
+    args: &ClientInstallation,
+    progress: ProgressCallback<'_>,
+) -> Result<String> {
+    if let Some(bundle) = &args.bundle {
+        let response = bundle::read(bundle, bundle::PROFILE_JSON)?;
+        validate_profile_json(&response)?;
+        return Ok(response);
+    }
+    if let Some(maven_loader) = &args.maven_loader {
+        return adapt_maven_profile_json(client, args, maven_loader, progress).await;
+    }
+
+    let cache_key = format!(
+        "profiles/{}/{}.json",
+        args.minecraft_version.version, args.loader_version.version
+    );
+    if args.offline {
+        let response = cache::read(&cache_key).with_context(|| {
+            format!(
+                "The profile for Quilt Loader {} and Minecraft {} is not cached, install it online once first",
+                args.loader_version, args.minecraft_version
+            )
+        })?;
+        validate_profile_json(&response)?;
+        return Ok(response);
+    }
+
+    let response = match download_profile_json(
+        client,
+        &args.meta_urls,
+        &args.minecraft_version,
+        &args.loader_version,
+        progress,
+    )
+    .await
+    {
+        Err(error) if error.is::<VersionUnavailable>() && args.allow_unsupported => {
+            eprintln!(
+                "Warning: Quilt Loader {} has no profile for Minecraft {}, \
+                adapting one from a supported version. It may not launch",
+                args.loader_version, args.minecraft_version
+            );
+            return adapt_profile_json(client, args, progress).await;
+        }
+        response => response?,
+    };
+    if let Some(locked) = &args.locked_json {
+        locked.verify(&response, &args.minecraft_version, &args.loader_version)?;
+    }
+    cache::write(&cache_key, &response);
+    Ok(response)
+}
+
+/// Builds a profile for a Minecraft version meta doesn't support from the one for the newest
+/// Minecraft version it does, pointed at the unsupported version and its mappings
+async fn adapt_profile_json(
+    client: &Client,
+    args: &ClientInstallation,
+    progress: ProgressCallback<'_>,
+) -> Result<String> {
+    let base_version = fetch_minecraft_versions(client.clone(), &args.meta_urls)
+        .await?
+        .into_iter()
+        .next()
+        .context("Quilt meta lists no Minecraft versions")?;
+    let response = download_profile_json(
+        client,
+        &args.meta_urls,
+        &base_version,
+        &args.loader_version,
+        progress,
+    )
+    .await?;
+
+    let mut json: Value = serde_json::from_str(&response)?;
+    json["id"] = profile_id(&args.loader_version, &args.minecraft_version).into();
+    json["inheritsFrom"] = args.minecraft_version.version.clone().into();
+    if let Some(libraries) = json["libraries"].as_array_mut() {
+        for library in libraries {
+            let Some(name) = library["name"].as_str() else {
+                continue;
+            };
+            // The mappings are the only libraries published for each Minecraft version
+            let Some(artifact) = ["org.quiltmc:hashed:", "net.fabricmc:intermediary:"]
+                .into_iter()
+                .find(|artifact| name == format!("{artifact}{}", base_version.version))
+            else {
+                continue;
+            };
+            library["name"] = format!("{artifact}{}", args.minecraft_version.version).into();
+        }
+    }
+    Ok(serde_json::to_string(&json)?)
+}
+
+/// Builds a profile for a loader build meta doesn't list from the profile of a version it does,
+/// with the loader library pointed at the build's maven coordinate and repository
+async fn adapt_maven_profile_json(
+    client: &Client,
+    args: &ClientInstallation,
+    maven_loader: &MavenLoader,
+    progress: ProgressCallback<'_>,
+) -> Result<String> {
+    let response = download_profile_json(
+        client,
+        &args.meta_urls,
+        &args.minecraft_version,
+        &maven_loader.base_version,
+        progress,
+    )
+    .await?;
+    if let Some(locked) = &args.locked_json {
+        locked.verify(
+            &response,
+            &args.minecraft_version,
+            &maven_loader.base_version,
+        )?;
+    }
+
+    let mut json: Value = serde_json::from_str(&response)?;
+    json["id"] = profile_id(&args.loader_version, &args.minecraft_version).into();
+    let loader_library = json["libraries"]
+        .as_array_mut()
+        .into_iter()
+        .flatten()
+        .find(|library| library["name"] == maven_loader.base_version.maven.as_str())
+        .with_context(|| {
+            format!(
+                "The profile of Quilt Loader {} has no {} library to replace",
+                maven_loader.base_version, maven_loader.base_version.maven
+            )
+        })?;
+    loader_library["name"] = args.loader_version.maven.clone().into();
+    loader_library["url"] = maven_loader.repository.clone().into();
+    Ok(serde_json::to_string(&json)?)
+}
+
+/// Downloads and validates the client launch json from meta
+pub async fn download_profile_json(
+    client: &Client,
+    meta_urls: &[String],
+    minecraft_version: &MinecraftVersion,
+    loader_version: &LoaderVersion,
+    progress: ProgressCallback<'_>,
+) -> Result<String> {
+    report(
+        progress,
+        InstallProgress::Indeterminate(InstallStep::DownloadProfileJson),
+    );
+    let response = with_meta_mirrors(meta_urls, |meta_url| async move {
+        download_with_progress(
+            client,
+            &format!(
+                "{meta_url}/v3/versions/loader/{}/{}/profile/json",
+                &minecraft_version.version, &loader_version.version
+            ),
+            InstallStep::DownloadProfileJson,
+            progress,
+        )
+        .await
+    })
+    .await;
+    let response = match response {
+        Err(error) if is_not_found(&error) => {
+            return Err(unsupported_combination_error(
+                client,
+                meta_urls,
+                minecraft_version,
+                loader_version,
+            )
+            .await)
+        }
+        response => String::from_utf8(response?)?,
+    };
+    report(
+        progress,
+        InstallProgress::StepCompleted(InstallStep::DownloadProfileJson),
+    );
+
+    validate_profile_json(&response)?;
+    Ok(response)
+}
+
+/// Downloads the server launch json from meta
+pub async fn download_server_json(
+    client: &Client,
+    meta_urls: &[String],
+    minecraft_version: &MinecraftVersion,
+    loader_version: &LoaderVersion,
+) -> Result<String> {
+    let response = with_meta_mirrors(meta_urls, |meta_url| async move {
+        let response = send(client.get(format!(
+            "{meta_url}/v3/versions/loader/{}/{}/server/json",
+            &minecraft_version.version, &loader_version.version
+        )))
+        .await?;
+        Ok(check_meta_response(response)?.text().await?)
+    })
+    .await;
+    match response {
+        Err(error) if is_not_found(&error) => {
+            Err(
+                unsupported_combination_error(client, meta_urls, minecraft_version, loader_version)
+                    .await,
+            )
+        }
+        response => response,
+    }
+}
+
+/// Catches meta responding with something that isn't a launchable profile, like an error page
+fn validate_profile_json(response: &str) -> Result<()> {
+    let json: Value = serde_json::from_str(response).context("Profile json appears invalid")?;
+    let has = |key: &str| json.get(key).is_some();
+    if !(has("id")
+        && has("mainClass")
+        && json.get("libraries").is_some_and(Value::is_array)
+        && (has("arguments") || has("minecraftArguments")))
+    {
+        bail!("Profile json appears invalid");
+    }
+
+    Ok(())
+}
+
+/// Checks that installed client profiles are intact and prints a summary of each.
+/// Every Quilt profile in the versions directory is checked unless one is named
+pub fn verify_client(
+    install_dir: &Path,
+    versions_dir: &Path,
+    profile_name: Option<&str>,
+) -> Result<()> {
+    let launcher_profiles: Option<LauncherProfiles> =
+        fs::read(install_dir.join("launcher_profiles.json"))
+            .ok()
+            .and_then(|json| {
+                parse_launcher_profiles(&json, &install_dir.join("launcher_profiles.json")).ok()
+            });
+
+    let profile_names = match profile_name {
+        Some(name) => vec![name.to_owned()],
+        None => {
+            let mut names: Vec<String> = fs::read_dir(versions_dir)
+                .with_context(|| format!("Could not read {}", versions_dir.display()))?
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|name| name.starts_with("quilt-loader-"))
+                .collect();
+            names.sort();
+            names
+        }
+    };
+    if profile_names.is_empty() {
+        bail!(
+            "No Quilt profiles are installed in {}",
+            versions_dir.display()
+        );
+    }
+
+    let mut broken = 0;
+    for name in &profile_names {
+        let mut problems = Vec::new();
+        match fs::read_to_string(versions_dir.join(name).join(format!("{name}.json"))) {
+            Err(_) => problems.push("the version json is missing"),
+            Ok(json) => {
+                if validate_profile_json(&json).is_err() {
+                    problems.push("the version json is invalid");
+                }
+            }
+        }
+        if let Some(launcher_profiles) = &launcher_profiles {
+            if !launcher_profiles
+                .profiles
+                .values()
+                .any(|profile| profile.last_version_id == *name)
+            {
+                problems.push("there is no launcher profile using it");
+            }
+        }
+
+        if problems.is_empty() {
+            println!("{name}: OK");
+        } else {
+            broken += 1;
+            println!("{name}: {}", problems.join(", "));
+        }
+    }
+
+    if launcher_profiles.is_none() {
+        bail!(
+            "launcher_profiles.json in {} is missing or invalid",
+            install_dir.display()
+        );
+    }
+    if broken > 0 {
+        bail!(
+            "{broken} of {} Quilt profiles have problems, reinstalling them should fix them",
+            profile_names.len()
+        );
+    }
+    println!("All Quilt profiles are intact.");
+    Ok(())
+}
+
+/// A Quilt client version found in a versions directory
+#[derive(Debug, Clone)]
+pub struct InstalledProfile {
+    pub id: String,
+    /// The Minecraft version the launch json inherits from, if it could be read
+    pub minecraft_version: Option<String>,
+    /// The version of the Quilt Loader library in the launch json, if it could be read
+    pub loader_version: Option<String>,
+}
+
+/// The Quilt client versions installed in `versions_dir`, sorted by id
+pub fn installed_client_profiles(versions_dir: &Path) -> Result<Vec<InstalledProfile>> {
+    let mut profiles: Vec<InstalledProfile> = fs::read_dir(versions_dir)
+        .with_context(|| format!("Could not read {}", versions_dir.display()))?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|id| id.starts_with("quilt-loader-"))
+        .map(|id| {
+            let json: Value = fs::read_to_string(versions_dir.join(&id).join(format!("{id}.json")))
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default();
+            let loader_version = json["libraries"].as_array().and_then(|libraries| {
+                libraries.iter().find_map(|library| {
+                    library["name"]
+                        .as_str()?
+                        .strip_prefix("org.quiltmc:quilt-loader:")
+                        .map(str::to_owned)
+                })
+            });
+            InstalledProfile {
+                minecraft_version: json["inheritsFrom"].as_str().map(str::to_owned),
+                loader_version,
+                id,
+            }
+        })
+        .collect();
+    profiles.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(profiles)
+}
+
+/// Removes installed Quilt client versions, and the launcher profiles using them
+/// from `profiles_file` if there is one
+pub fn uninstall_client(
+    versions_dir: &Path,
+    profiles_file: &Path,
+    ids: &[String],
+    brief: bool,
+) -> Result<()> {
+    for id in ids {
+        let dir = extended_length_path(versions_dir).join(id);
+        fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+        if brief {
+            println!("uninstalled {id}");
+        } else {
+            println!("Removed {id}");
+        }
+    }
+
+    if !profiles_file.exists() {
+        return Ok(());
+    }
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(profiles_file)
+        .map_err(|error| profiles_file_error(error, profiles_file))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)
+        .map_err(|error| profiles_file_error(error, profiles_file))?;
+    let mut launcher_profiles = parse_launcher_profiles(&contents, profiles_file)?;
+    let count = launcher_profiles.profiles.len();
+    launcher_profiles
+        .profiles
+        .retain(|_, profile| !ids.contains(&profile.last_version_id));
+    if launcher_profiles.profiles.len() == count {
+        return Ok(());
+    }
+    back_up_unreadable_profiles(&contents, profiles_file)?;
+    file.set_len(0)
+        .and_then(|_| file.rewind())
+        .map_err(|error| profiles_file_error(error, profiles_file))?;
+    serde_json::to_writer_pretty(file, &launcher_profiles)
+        .map_err(|error| profiles_file_error(error.into(), profiles_file))?;
+    if !brief {
+        println!(
+            "Removed {} launcher profiles using them",
+            count - launcher_profiles.profiles.len()
+        );
+    }
+    Ok(())
+}
+
+/// Reads a launcher_profiles.json the same way installing does and reports what's in it,
+/// without changing it, for triaging broken profile files
+pub fn validate_launcher_profiles(path: &Path) -> Result<()> {
+    let contents = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let launcher_profiles = parse_launcher_profiles(&contents, path)?;
+    let count = launcher_profiles.profiles.len();
+    println!(
+        "{} is valid and has {count} profile{}",
+        path.display(),
+        if count == 1 { "" } else { "s" }
+    );
+
+    let mut quilt_profiles: Vec<_> = launcher_profiles
+        .profiles
+        .iter()
+        .filter(|(_, profile)| profile.last_version_id.starts_with("quilt-loader-"))
+        .collect();
+    quilt_profiles.sort_by_key(|(key, _)| *key);
+    if quilt_profiles.is_empty() {
+        println!("No Quilt profiles found.");
+    }
+    for (key, profile) in quilt_profiles {
+        println!(
+            "{key}: \"{}\" using {}, created {}",
+            profile.name,
+            profile.last_version_id,
+            profile.created.format("%Y-%m-%d")
+        );
+    }
+    Ok(())
+}
+
+pub async fn install_server(
+    client: Client,
+    args: ServerInstallation,
+    progress: ProgressCallback<'_>,
+) -> Result<()> {
+    if !args.brief {
+        println!("Installing server {args}");
+    }
+
+    if args.systemd_service {
+        if !cfg!(target_os = "linux") {
+            bail!("systemd services can only be generated on Linux");
+        }
+        if !args.generate_script {
+            bail!("The systemd service needs the launch script to be generated");
+        }
+    }
+
+    // Create install and output directories
+    let is_new_install =
+        fs::read_dir(&args.install_dir).map_or(true, |mut entries| entries.next().is_none());
+    let output_dir = args.resolve_output_dir();
+    for dir in [&args.install_dir, &output_dir] {
+        if dir.exists() && !dir.is_dir() {
+            bail!("{} is not a directory", dir.display());
+        }
+    }
+    if let Some(warning) = volume::install_dir_warning(&output_dir) {
+        eprintln!("Warning: {warning}");
+    }
+    fs::create_dir_all(&args.install_dir)?;
+    fs::create_dir_all(&output_dir)?;
+
+    // The jar download needs the version manifest anyway, the loader age check alone
+    // shouldn't hold up the install
+    let manifest = match &args.bundle {
+        Some(_) => None,
+        None if args.download_jar => {
+            Some(fetch_version_manifest(&client, &args.mojang_meta_url).await?)
+        }
+        None => fetch_version_manifest_briefly(&client, &args.mojang_meta_url)
+            .await
+            .ok(),
+    };
+    if let Some(manifest) = &manifest {
+        warn_if_loader_predates(
+            &client,
+            manifest,
+            QUILT_MAVEN_URL,
+            &args.minecraft_version,
+            &args.loader_version,
+        )
+        .await;
+    }
+
+    // Download server launch json
+    let response = match &args.bundle {
+        Some(bundle) => bundle::read(bundle, bundle::SERVER_JSON)?,
+        None => {
+            let response = download_server_json(
+                &client,
+                &args.meta_urls,
+                &args.minecraft_version,
+                &args.loader_version,
+            )
+            .await?;
+            if let Some(locked) = &args.locked_json {
+                locked.verify(&response, &args.minecraft_version, &args.loader_version)?;
+            }
+            response
+        }
+    };
+    let launch_json: ServerLaunchJson = serde_json::from_str(&response)?;
+
+    // Download libraries
+    report(
+        progress,
+        InstallProgress::Indeterminate(InstallStep::DownloadLibraries),
+    );
+    let libraries = library_downloads(&response)?;
+    let library_count = libraries.len();
+    let mut class_path = Vec::with_capacity(library_count);
+    for (i, (url, path)) in libraries.into_iter().enumerate() {
+        let destination = output_dir.join("libraries").join(&path);
+        match &args.bundle {
+            Some(bundle) => bundle::copy_library(bundle, &path, &destination)?,
+            None if args.shared_cache => {
+                download_shared_library(&client, &url, &destination).await?
+            }
+            None => {
+                download_file(
+                    &client,
+                    &url,
+                    &destination,
+                    InstallStep::DownloadLibraries,
+                    None,
+                )
+                .await?
+            }
+        }
+        class_path.push(format!("libraries/{path}"));
+        report(
+            progress,
+            InstallProgress::Determinate(
+                InstallStep::DownloadLibraries,
+                (i + 1) as f32 / library_count as f32,
+            ),
+        );
+    }
+    report(
+        progress,
+        InstallProgress::StepCompleted(InstallStep::DownloadLibraries),
+    );
+
+    // Create launch jar
+    let launch_jar = output_dir.join(args.launch_jar_name.as_deref().unwrap_or(SERVER_LAUNCH_JAR));
+    write_launch_jar(
+        &launch_jar,
+        launch_json
+            .launcher_main_class
+            .as_deref()
+            .unwrap_or(&launch_json.main_class),
+        &class_path,
+    )?;
+    fs::write(
+        output_dir.join("quilt-server-launcher.properties"),
+        format!("serverJar={VANILLA_SERVER_JAR}\n"),
+    )?;
+
+    // Download vanilla server jar
+    if args.download_jar {
+        report(
+            progress,
+            InstallProgress::Indeterminate(InstallStep::DownloadServerJar),
+        );
+        let destination = output_dir.join(VANILLA_SERVER_JAR);
+        if let Some(bundle) = &args.bundle {
+            fs::copy(bundle.join(bundle::SERVER_JAR), &destination)
+                .context("The bundle is missing the server jar")?;
+        } else {
+            let manifest = match manifest {
+                Some(manifest) => manifest,
+                None => fetch_version_manifest(&client, &args.mojang_meta_url).await?,
+            };
+            let download =
+                fetch_vanilla_server_download(&client, &manifest, &args.minecraft_version).await?;
+            // An upgrade of Quilt for the same Minecraft version can keep the jar it has
+            let reuse = match &download.sha1 {
+                Some(sha1) if !args.force_download => has_sha1(&destination, sha1)?,
+                _ => false,
+            };
+            if reuse {
+                if !args.brief {
+                    println!(
+                        "Reusing the existing {VANILLA_SERVER_JAR}, it is already the right one"
+                    );
+                }
+            } else {
+                if destination.exists() && !args.brief {
+                    println!("Replacing the existing {VANILLA_SERVER_JAR}");
+                }
+                download_file(
+                    &client,
+                    &download.url,
+                    &destination,
+                    InstallStep::DownloadServerJar,
+                    progress,
+                )
+                .await?;
+            }
+        }
+        report(
+            progress,
+            InstallProgress::StepCompleted(InstallStep::DownloadServerJar),
+        );
+    }
+
+    // Generate launch scripts
+    if args.generate_script {
+        let java = args
+            .java_path
+            .as_ref()
+            .map_or_else(|| "java".into(), |path| path.display().to_string());
+        let launch_jar_name = launch_jar
+            .file_name()
+            .context("Invalid launch jar name")?
+            .to_string_lossy();
+        write_launch_scripts(&args.install_dir, &java, &launch_jar_name, args.all_scripts)?;
+    }
+
+    // Generate readme
+    if args.write_readme.unwrap_or(is_new_install) {
+        write_server_readme(&args, &launch_jar)?;
+    }
+
+    // Generate systemd service
+    if args.systemd_service {
+        let service = write_systemd_service(&args.install_dir, args.systemd_user.as_deref())?;
+        if !args.brief {
+            if args.systemd_user.is_none() {
+                println!(
+                    "Set User= in {} to the account the server should run as, \
+                    it won't start until then",
+                    service.display()
+                );
+            }
+            println!(
+                "To run the server as a service, copy {} to /etc/systemd/system/ \
+            and run `systemctl enable --now {SYSTEMD_SERVICE}`",
+                service.display()
+            );
+        }
+    }
+
+    if args.brief {
+        println!(
+            "installed {}-server -> {}",
+            profile_id(&args.loader_version, &args.minecraft_version),
+            args.install_dir.display()
+        );
+    } else {
+        println!("Server installed successfully.");
+    }
+    Ok(())
+}
+
+pub const MOJANG_VERSION_MANIFEST_URL: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+/// The maven repository Quilt Loader releases are published to
+pub const QUILT_MAVEN_URL: &str = "https://maven.quiltmc.org/repository/release/";
+/// How long before a Minecraft version's release a loader build can be published
+/// before it likely predates the mappings the version needs
+const LOADER_AGE_WARNING_DAYS: i64 = 90;
+/// How long the loader age check waits for an answer before it's skipped
+const LOADER_AGE_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+const SERVER_LAUNCH_JAR: &str = "quilt-server-launch.jar";
+const SYSTEMD_SERVICE: &str = "quilt-server.service";
+const SERVER_README: &str = "QUILT-SERVER-README.txt";
+const VANILLA_SERVER_JAR: &str = "server.jar";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerLaunchJson {
+    main_class: String,
+    launcher_main_class: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Library {
+    name: String,
+    url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VersionManifest {
+    versions: Vec<VersionManifestEntry>,
+}
+
+impl VersionManifest {
+    fn find(&self, minecraft_version: &MinecraftVersion) -> Option<&VersionManifestEntry> {
+        self.versions
+            .iter()
+            .find(|v| v.id == minecraft_version.version)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VersionManifestEntry {
+    id: String,
+    url: String,
+    #[serde(rename = "releaseTime", default)]
+    release_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VersionMeta {
+    downloads: HashMap<String, VersionDownload>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct VersionDownload {
+    url: String,
+    sha1: Option<String>,
+}
+
+/// The download URL and maven path of each library in a client or server launch json
+pub fn library_downloads(launch_json: &str) -> Result<Vec<(String, String)>> {
+    #[derive(Deserialize)]
+    struct LaunchJson {
+        libraries: Vec<Library>,
+    }
+
+    let launch_json: LaunchJson = serde_json::from_str(launch_json)?;
+    launch_json
+        .libraries
+        .iter()
+        .map(|library| {
+            let path = maven_path(&library.name)?;
+            Ok((format!("{}{path}", library.url), path))
+        })
+        .collect()
+}
+
+/// Converts a maven coordinate (`group:artifact:version`) into its repository path
+fn maven_path(name: &str) -> Result<String> {
+    let mut parts = name.splitn(3, ':');
+    let (Some(group), Some(artifact), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        bail!("Invalid maven coordinate {name}");
+    };
+
+    Ok(format!(
+        "{}/{artifact}/{version}/{artifact}-{version}.jar",
+        group.replace('.', "/")
+    ))
+}
+
+/// The most bytes per second each download may use, unlimited unless set
+static MAX_DOWNLOAD_RATE: OnceLock<u64> = OnceLock::new();
+
+/// Throttles all downloads to `bytes_per_second`, so installing doesn't saturate a shared
+/// connection. Only the first call has an effect
+pub fn set_max_download_rate(bytes_per_second: u64) {
+    let _ = MAX_DOWNLOAD_RATE.set(bytes_per_second);
+}
+
+/// Downloads a file, reporting how much of it is done as the progress of `step`
+/// if the server says how large it is
+async fn download_with_progress(
+    client: &Client,
+    url: &str,
+    step: InstallStep,
+    progress: ProgressCallback<'_>,
+) -> Result<Vec<u8>> {
+    let mut response = send(client.get(url)).await?.error_for_status()?;
+    // None of the downloaded files are web pages, so one is likely a sign-in page
+    if has_content_type(&response, "html") {
+        bail!(
+            "Downloading {url} returned a web page from {}. {CAPTIVE_PORTAL_HINT}",
+            response.url()
+        );
+    }
+    let total = response.content_length();
+
+    let start = Instant::now();
+    let mut bytes = Vec::with_capacity(total.unwrap_or_default() as usize);
+    while let Some(chunk) = response.chunk().await? {
+        bytes.extend_from_slice(&chunk);
+        if let Some(&rate) = MAX_DOWNLOAD_RATE.get() {
+            // Sleep until the average rate since the start is back down to the limit
+            let expected = Duration::from_secs_f64(bytes.len() as f64 / rate as f64);
+            if let Some(ahead) = expected.checked_sub(start.elapsed()) {
+                tokio::time::sleep(ahead).await;
+            }
+        }
+        if let Some(total) = total.filter(|&total| total > 0) {
+            report(
+                progress,
+                InstallProgress::Determinate(step, bytes.len() as f32 / total as f32),
+            );
+        }
+    }
+    if TRACE_REQUESTS.load(Ordering::Relaxed) {
+        eprintln!("[request] received {} bytes from {url}", bytes.len());
+    }
+
+    Ok(bytes)
+}
+
+pub async fn download_file(
+    client: &Client,
+    url: &str,
+    path: &Path,
+    step: InstallStep,
+    progress: ProgressCallback<'_>,
+) -> Result<()> {
+    let bytes = download_with_progress(client, url, step, progress).await?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // The file may be a hard link into the shared cache, left by an install with it.
+    // Writing through it would change the artifact every other install links to
+    match fs::remove_file(path) {
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+        result => result?,
+    }
+    fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+pub async fn fetch_vanilla_server_url(
+    client: &Client,
+    manifest_url: &str,
+    minecraft_version: &MinecraftVersion,
+) -> Result<String> {
+    let manifest = fetch_version_manifest(client, manifest_url).await?;
+    Ok(
+        fetch_vanilla_server_download(client, &manifest, minecraft_version)
+            .await?
+            .url,
+    )
+}
+
+async fn fetch_version_manifest(client: &Client, manifest_url: &str) -> Result<VersionManifest> {
+    Ok(send(client.get(manifest_url))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// Fetches the version manifest with a single short request, for checks an install
+/// shouldn't wait on or retry for
+async fn fetch_version_manifest_briefly(
+    client: &Client,
+    manifest_url: &str,
+) -> Result<VersionManifest> {
+    Ok(
+        send_once(client.get(manifest_url).timeout(LOADER_AGE_CHECK_TIMEOUT))
+            .await?
+            .error_for_status()?
+            .json()
+            .await?,
+    )
+}
+
+async fn fetch_vanilla_server_download(
+    client: &Client,
+    manifest: &VersionManifest,
+    minecraft_version: &MinecraftVersion,
+) -> Result<VersionDownload> {
+    let entry = manifest.find(minecraft_version).with_context(|| {
+        format!("Could not find Minecraft {minecraft_version} in the Mojang version manifest")
+    })?;
+
+    let mut meta: VersionMeta = send(client.get(&entry.url))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    meta.downloads
+        .remove("server")
+        .with_context(|| format!("Minecraft {minecraft_version} has no server download"))
+}
+
+/// Warns when the loader build was published long before the Minecraft version came out.
+/// Meta may still serve a profile for the pair, but one relying on mappings the loader
+/// predates won't launch. The check is only advisory, so failing to find a date skips it
+async fn warn_if_loader_predates(
+    client: &Client,
+    manifest: &VersionManifest,
+    repository: &str,
+    minecraft_version: &MinecraftVersion,
+    loader_version: &LoaderVersion,
+) {
+    let Some(released) = manifest
+        .find(minecraft_version)
+        .and_then(|entry| entry.release_time)
+    else {
+        return;
+    };
+    let Ok(Some(published)) = fetch_publish_time(client, repository, loader_version).await else {
+        return;
+    };
+    let days = (released - published).num_days();
+    if days > LOADER_AGE_WARNING_DAYS {
+        eprintln!(
+            "Warning: Quilt Loader {loader_version} was published {days} days before \
+            Minecraft {minecraft_version} came out, and may not support it. \
+            Pick a newer loader version if the game doesn't launch"
+        );
+    }
+}
+
+/// When the loader build was published, going by when its pom was last modified in the repository,
+/// as meta doesn't say when builds were made. It's asked once and briefly, like the manifest
+async fn fetch_publish_time(
+    client: &Client,
+    repository: &str,
+    loader_version: &LoaderVersion,
+) -> Result<Option<DateTime<Utc>>> {
+    let jar_path = maven_path(&loader_version.maven)?;
+    let pom_path = jar_path.strip_suffix(".jar").unwrap_or(&jar_path);
+    let url = format!("{}/{pom_path}.pom", repository.trim_end_matches('/'));
+    let response = send_once(client.get(url).timeout(LOADER_AGE_CHECK_TIMEOUT))
+        .await?
+        .error_for_status()?;
+    Ok(response
+        .headers()
+        .get(LAST_MODIFIED)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+        .map(|time| time.with_timezone(&Utc)))
+}
+
+/// Downloads a library into the shared cache, which keeps one copy of each jar by its SHA-1,
+/// unless it's already there, and links it to `destination`. Libraries whose repository
+/// doesn't publish a hash for them are downloaded without the cache
+async fn download_shared_library(client: &Client, url: &str, destination: &Path) -> Result<()> {
+    let Some(sha1) = fetch_published_sha1(client, url).await else {
+        return download_file(
+            client,
+            url,
+            destination,
+            InstallStep::DownloadLibraries,
+            None,
+        )
+        .await;
+    };
+    let artifact = cache::artifact_path(&sha1);
+    if !has_sha1(&artifact, &sha1)? {
+        download_file(client, url, &artifact, InstallStep::DownloadLibraries, None).await?;
+        if !has_sha1(&artifact, &sha1)? {
+            let _ = fs::remove_file(&artifact);
+            bail!("{url} does not match the SHA-1 hash its repository publishes");
+        }
+    }
+    cache::link_artifact(&artifact, destination)
+}
+
+/// The SHA-1 hash a maven repository publishes next to an artifact, in `<url>.sha1`
+async fn fetch_published_sha1(client: &Client, url: &str) -> Option<String> {
+    let response = send(client.get(format!("{url}.sha1")))
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+    // Some repositories write the file name after the hash
+    let text = response.text().await.ok()?;
+    let sha1 = text.split_whitespace().next()?.to_ascii_lowercase();
+    (sha1.len() == 40 && sha1.bytes().all(|b| b.is_ascii_hexdigit())).then_some(sha1)
+}
+
+/// Whether the file is there and has the expected SHA-1 hash
+fn has_sha1(path: &Path, expected: &str) -> Result<bool> {
+    use sha1::{Digest, Sha1};
+
+    if !path.is_file() {
+        return Ok(false);
+    }
+    let mut hasher = Sha1::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()).eq_ignore_ascii_case(expected))
+}
+
+fn write_launch_jar(path: &Path, main_class: &str, class_path: &[String]) -> Result<()> {
+    let mut manifest = String::new();
+    write_manifest_attribute(&mut manifest, "Manifest-Version", "1.0");
+    write_manifest_attribute(&mut manifest, "Main-Class", main_class);
+    write_manifest_attribute(&mut manifest, "Class-Path", &class_path.join(" "));
+
+    let mut jar = zip::ZipWriter::new(File::create(path)?);
+    jar.start_file(
+        "META-INF/MANIFEST.MF",
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored),
+    )?;
+    jar.write_all(manifest.as_bytes())?;
+    jar.finish()?;
+
+    Ok(())
+}
+
+/// Jar manifests wrap lines at 72 bytes, continuing with a leading space
+fn write_manifest_attribute(manifest: &mut String, key: &str, value: &str) {
+    let line = format!("{key}: {value}");
+    let mut rest = line.as_str();
+    let mut width = 72;
+    while !rest.is_empty() {
+        // The limit is in bytes, so multi-byte characters are kept whole on the next line
+        let mut split = rest.len().min(width);
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        if width < 72 {
+            manifest.push(' ');
+        }
+        manifest.push_str(&rest[..split]);
+        manifest.push_str("\r\n");
+        rest = &rest[split..];
+        width = 71;
+    }
+}
+
+fn write_launch_scripts(
+    install_dir: &Path,
+    java: &str,
+    launch_jar_name: &str,
+    all_scripts: bool,
+) -> Result<()> {
+    let command = format!("\"{java}\" -Xmx2G -jar \"{launch_jar_name}\" nogui");
+
+    // Only the scripts for the OS installed on are written, unless the server is meant for another
+    if cfg!(target_os = "windows") || all_scripts {
+        fs::write(
+            install_dir.join("start.bat"),
+            format!("@echo off\r\n{command}\r\npause\r\n"),
+        )?;
+    }
+
+    if !cfg!(target_os = "windows") || all_scripts {
+        write_executable(
+            &install_dir.join("start.sh"),
+            &format!("#!/usr/bin/env sh\n{command}\n"),
+        )?;
+    }
+
+    // Finder runs .command files on double-click, but from the user's home directory
+    if cfg!(target_os = "macos") || all_scripts {
+        write_executable(
+            &install_dir.join("start.command"),
+            &format!("#!/usr/bin/env sh\ncd \"$(dirname \"$0\")\"\n{command}\n"),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn write_server_readme(args: &ServerInstallation, launch_jar: &Path) -> Result<()> {
+    let start = if args.generate_script && args.all_scripts {
+        "To start the server, run start.bat on Windows or start.sh on Linux and macOS.".into()
+    } else if args.generate_script && cfg!(target_os = "windows") {
+        "To start the server, run start.bat.".into()
+    } else if args.generate_script {
+        "To start the server, run start.sh.".into()
+    } else {
+        format!(
+            "To start the server, run `java -jar \"{}\" nogui` from this directory.",
+            launch_jar.display()
+        )
+    };
+    let server_jar = if args.download_jar {
+        ""
+    } else {
+        "The vanilla Minecraft server jar wasn't downloaded. \
+        Place it next to the launch jar as server.jar before starting the server.\n\n"
+    };
+
+    fs::write(
+        args.install_dir.join(SERVER_README),
+        format!(
+            "Quilt server for Minecraft {}, with Quilt Loader {}\n\
+            \n\
+            {start}\n\
+            \n\
+            {server_jar}\
+            Put mods into the mods folder in this directory. \
+            It is created the first time the server starts.\n",
+            args.minecraft_version, args.loader_version,
+        ),
+    )?;
+
+    Ok(())
+}
+
+/// Stands in for the account to run the server as until it's filled in. No such account exists,
+/// so the service fails to start rather than running the server as root
+const SYSTEMD_USER_PLACEHOLDER: &str = "CHANGE-ME";
+
+fn write_systemd_service(install_dir: &Path, user: Option<&str>) -> Result<PathBuf> {
+    let install_dir = install_dir.canonicalize()?;
+    let service = install_dir.join(SYSTEMD_SERVICE);
+    let user = match user {
+        Some(user) => format!("User={user}\n"),
+        None => format!(
+            "# The account the server runs as, services without one run as root\n\
+            User={SYSTEMD_USER_PLACEHOLDER}\n"
+        ),
+    };
+    fs::write(
+        &service,
+        format!(
+            "[Unit]\n\
+            Description=Quilt Minecraft server\n\
+            After=network.target\n\
+            \n\
+            [Service]\n\
+            {user}\
+            WorkingDirectory={}\n\
+            ExecStart=\"{}\"\n\
+            Restart=on-failure\n\
+            \n\
+            [Install]\n\
+            WantedBy=multi-user.target\n",
+            install_dir.display(),
+            install_dir.join("start.sh").display(),
+        ),
+    )?;
+
+    Ok(service)
+}
+
+fn write_executable(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loader(version: &str) -> LoaderVersion {
+        LoaderVersion {
+            separator: '.',
+            build: 0,
+            maven: format!("org.quiltmc:quilt-loader:{version}"),
+            version: Version::parse(version).unwrap(),
+        }
+    }
+
+    fn minecraft(version: &str) -> MinecraftVersion {
+        MinecraftVersion {
+            version: version.into(),
+            stable: true,
+            recommended: false,
+        }
+    }
+
+    #[test]
+    fn profile_id_contains_both_versions() {
+        assert_eq!(
+            profile_id(&loader("0.21.0"), &minecraft("1.20.4")),
+            "quilt-loader-0.21.0-1.20.4"
+        );
+        assert_eq!(
+            profile_id(&loader("0.22.0-beta.1"), &minecraft("24w03a")),
+            "quilt-loader-0.22.0-beta.1-24w03a"
+        );
+    }
+
+    #[test]
+    fn only_quilt_loader_skips_other_artifacts() {
+        let mut other_group = loader("0.21.1");
+        other_group.maven = "com.example:quilt-loader:0.21.1".into();
+        let mut other_artifact = loader("0.21.2");
+        other_artifact.maven = "org.quiltmc:quilt-loader-evil:0.21.2".into();
+        assert_eq!(
+            only_quilt_loader(vec![loader("0.21.0"), other_group, other_artifact]),
+            vec![loader("0.21.0")]
+        );
+    }
+
+    #[test]
+    fn reformat_json_only_changes_whitespace() {
+        let json = r#"{"id": "quilt", "args" :[ ], "libraries":[{"url":"x, \"y\" {"}],"n":1.50}"#;
+        let pretty = reformat_json(json, true);
+        assert_eq!(
+            pretty,
+            r#"{
+  "id": "quilt",
+  "args": [],
+  "libraries": [
+    {
+      "url": "x, \"y\" {"
+    }
+  ],
+  "n": 1.50
+}"#
+        );
+        assert_eq!(
+            reformat_json(&pretty, false),
+            r#"{"id":"quilt","args":[],"libraries":[{"url":"x, \"y\" {"}],"n":1.50}"#
+        );
+    }
+
+    #[test]
+    fn parse_launcher_profiles_reads_legacy_files() {
+        let path = Path::new("launcher_profiles.json");
+        // A BOM, and a Windows-1252 "é" in a profile name
+        let contents = b"\xEF\xBB\xBF{\"profiles\": {\"caf\xE9\": {\"name\": \"Caf\xE9\", \
+            \"type\": \"custom\", \"created\": \"2023-01-01T00:00:00Z\", \
+            \"lastVersionId\": \"1.20.4\", \"icon\": \"Furnace\"}}}";
+        let profiles = parse_launcher_profiles(contents, path).unwrap();
+        assert_eq!(profiles.profiles["caf\u{FFFD}"].name, "Caf\u{FFFD}");
+
+        let error = parse_launcher_profiles(b"{\n  \"profiles\": [}", path).unwrap_err();
+        assert!(error.to_string().contains("at byte 17"), "{error}");
+
+        // Offsets count the file's own bytes, not the decoded text or its replacement characters
+        let error = parse_launcher_profiles(b"\xEF\xBB\xBF{\"\xE9\xE9\": [}", path).unwrap_err();
+        assert!(error.to_string().contains("at byte 11"), "{error}");
+    }
+
+    #[test]
+    fn maven_path_lays_out_coordinates_like_a_repository() {
+        assert_eq!(
+            maven_path("org.quiltmc:quilt-loader:0.21.0").unwrap(),
+            "org/quiltmc/quilt-loader/0.21.0/quilt-loader-0.21.0.jar"
+        );
+        assert!(maven_path("org.quiltmc:quilt-loader").is_err());
+    }
+
+    #[test]
+    fn manifest_lines_wrap_at_72_bytes() {
+        let mut manifest = String::new();
+        write_manifest_attribute(&mut manifest, "Class-Path", &"a".repeat(100));
+        let lines: Vec<&str> = manifest.split_terminator("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].len(), 72);
+        assert_eq!(lines[1], format!(" {}", "a".repeat(40)));
+
+        // A two byte character that would straddle the limit moves to the next line
+        let mut manifest = String::new();
+        write_manifest_attribute(&mut manifest, "Main-Class", &format!("{}é", "a".repeat(59)));
+        let lines: Vec<&str> = manifest.split_terminator("\r\n").collect();
+        assert_eq!(
+            lines,
+            [format!("Main-Class: {}", "a".repeat(59)), " é".into()]
+        );
+        assert!(lines.iter().all(|line| line.len() <= 72));
+    }
+
+    #[test]
+    fn profile_display_name_uses_minecraft_version() {
+        assert_eq!(
+            profile_display_name(&minecraft("1.20.4")),
+            "Quilt Loader 1.20.4"
+        );
+    }
+
+    #[test]
+    fn only_server_errors_and_rate_limits_are_retried() {
+        assert!(is_transient_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(is_transient_status(StatusCode::BAD_GATEWAY));
+        assert!(is_transient_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_transient_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_transient_status(StatusCode::OK));
+        assert!(!is_transient_status(StatusCode::NOT_FOUND));
+        assert!(!is_transient_status(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn invalid_requests_are_not_retried() {
+        let error = Client::new().get("not a url").build().unwrap_err();
+        assert!(!is_transient_error(&error));
+    }
+
+    #[test]
+    fn retries_back_off_until_used_up() {
+        assert_eq!(retry_delay(0, 0), None);
+        assert_eq!(retry_delay(0, 3), Some(RETRY_BACKOFF));
+        assert_eq!(retry_delay(1, 3), Some(RETRY_BACKOFF * 2));
+        assert_eq!(retry_delay(2, 3), Some(RETRY_BACKOFF * 4));
+        assert_eq!(retry_delay(3, 3), None);
+        // The backoff stops growing after a while
+        assert_eq!(retry_delay(10, 20), retry_delay(6, 20));
+    }
+
+    #[test]
+    fn profile_json_needs_a_libraries_array() {
+        let valid = r#"{"id":"a","mainClass":"b","arguments":{},"libraries":[]}"#;
+        assert!(validate_profile_json(valid).is_ok());
+        let not_an_array = r#"{"id":"a","mainClass":"b","arguments":{},"libraries":{}}"#;
+        assert!(validate_profile_json(not_an_array).is_err());
+        assert!(validate_profile_json("[]").is_err());
+    }
 }
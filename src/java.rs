@@ -0,0 +1,155 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JavaInstall {
+    pub major_version: u32,
+    pub path: PathBuf,
+}
+
+impl std::fmt::Display for JavaInstall {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Java {} ({})", self.major_version, self.path.display())
+    }
+}
+
+#[cfg(target_os = "windows")]
+const JAVA_BINARY: &str = "java.exe";
+
+#[cfg(not(target_os = "windows"))]
+const JAVA_BINARY: &str = "java";
+
+#[cfg(target_os = "windows")]
+fn get_jvm_directories() -> Vec<PathBuf> {
+    let program_files =
+        PathBuf::from(std::env::var("ProgramFiles").unwrap_or_else(|_| r"C:\Program Files".into()));
+    ["Java", "Eclipse Adoptium", "Microsoft", "Zulu", "BellSoft"]
+        .iter()
+        .map(|vendor| program_files.join(vendor))
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn get_jvm_directories() -> Vec<PathBuf> {
+    vec![
+        PathBuf::from("/Library/Java/JavaVirtualMachines"),
+        PathBuf::from(std::env::var("HOME").unwrap())
+            .join("Library")
+            .join("Java")
+            .join("JavaVirtualMachines"),
+    ]
+}
+
+#[cfg(target_os = "linux")]
+fn get_jvm_directories() -> Vec<PathBuf> {
+    vec![
+        PathBuf::from("/usr/lib/jvm"),
+        PathBuf::from("/usr/java"),
+        PathBuf::from("/opt/java"),
+    ]
+}
+
+/// Scans `JAVA_HOME`, `PATH` and the platform's common JDK locations for Java installations,
+/// newest first
+pub fn find_java_installations() -> Vec<JavaInstall> {
+    let mut candidates = Vec::new();
+    if let Some(home) = std::env::var_os("JAVA_HOME") {
+        candidates.push(Path::new(&home).join("bin").join(JAVA_BINARY));
+    }
+    if let Some(paths) = std::env::var_os("PATH") {
+        candidates.extend(std::env::split_paths(&paths).map(|dir| dir.join(JAVA_BINARY)));
+    }
+    for dir in get_jvm_directories() {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            // macOS bundles keep the actual java home inside the bundle
+            let mut home = entry.path();
+            if home.join("Contents").join("Home").is_dir() {
+                home = home.join("Contents").join("Home");
+            }
+            candidates.push(home.join("bin").join(JAVA_BINARY));
+        }
+    }
+
+    let mut seen = Vec::new();
+    let mut installs = Vec::new();
+    for path in candidates {
+        // Symlinks like /usr/bin/java usually point into one of the JDK directories
+        let Ok(canonical) = path.canonicalize() else {
+            continue;
+        };
+        if seen.contains(&canonical) {
+            continue;
+        }
+        seen.push(canonical);
+
+        if let Some(major_version) = probe_major_version(&path) {
+            installs.push(JavaInstall {
+                major_version,
+                path,
+            });
+        }
+    }
+
+    installs.sort_by(|a, b| b.major_version.cmp(&a.major_version));
+    installs
+}
+
+fn probe_major_version(java: &Path) -> Option<u32> {
+    // Reading the `release` file is much cheaper than spawning the JVM
+    let release = java
+        .parent()
+        .and_then(Path::parent)
+        .and_then(|home| fs::read_to_string(home.join("release")).ok());
+    if let Some(release) = release {
+        let version = release
+            .lines()
+            .find_map(|line| line.strip_prefix("JAVA_VERSION="))
+            .map(|version| version.trim_matches('"'));
+        if let Some(major_version) = version.and_then(parse_major_version) {
+            return Some(major_version);
+        }
+    }
+
+    let mut command = Command::new(java);
+    command.arg("-version");
+    #[cfg(target_os = "windows")]
+    {
+        // Don't flash a console window from the GUI
+        use std::os::windows::process::CommandExt;
+        command.creation_flags(0x08000000);
+    }
+
+    // `java -version` prints something like `openjdk version "21.0.1" 2023-10-17` to stderr
+    let output = command.output().ok()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let version = stderr.split('"').nth(1)?;
+    parse_major_version(version)
+}
+
+/// Handles both the legacy `1.8.0_392` and the modern `21.0.1` version schemes
+fn parse_major_version(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_major_version_handles_both_schemes() {
+        assert_eq!(parse_major_version("1.8.0_392"), Some(8));
+        assert_eq!(parse_major_version("17.0.2"), Some(17));
+        assert_eq!(parse_major_version("21"), Some(21));
+        assert_eq!(parse_major_version("unknown"), None);
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+#[cfg(target_os = "windows")]
+fn get_launcher_candidates() -> Vec<PathBuf> {
+    ["ProgramFiles(x86)", "ProgramFiles"]
+        .iter()
+        .filter_map(|var| std::env::var_os(var))
+        .map(|dir| {
+            PathBuf::from(dir)
+                .join("Minecraft Launcher")
+                .join("MinecraftLauncher.exe")
+        })
+        .collect()
+}
+
+#[cfg(target_os = "macos")]
+fn get_launcher_candidates() -> Vec<PathBuf> {
+    vec![
+        PathBuf::from("/Applications/Minecraft.app"),
+        PathBuf::from(std::env::var("HOME").unwrap())
+            .join("Applications")
+            .join("Minecraft.app"),
+    ]
+}
+
+#[cfg(target_os = "linux")]
+fn get_launcher_candidates() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join("minecraft-launcher"))
+                .collect()
+        })
+        .unwrap_or_default();
+    candidates.push(PathBuf::from("/opt/minecraft-launcher/minecraft-launcher"));
+    candidates
+}
+
+/// The official Minecraft launcher, if it is installed in one of the usual places
+pub fn find_launcher() -> Option<PathBuf> {
+    get_launcher_candidates()
+        .into_iter()
+        .find(|path| path.exists())
+}
+
+/// Starts the Minecraft launcher without waiting for it to exit,
+/// or prints a note if it can't be found
+pub fn open_launcher() {
+    let Some(launcher) = find_launcher() else {
+        println!("Could not find the Minecraft launcher, start it yourself to play.");
+        return;
+    };
+    if let Err(error) = spawn(&launcher) {
+        eprintln!(
+            "Failed to start the Minecraft launcher at {}: {error}",
+            launcher.display()
+        );
+    }
+}
+
+/// Opens a web page in the default browser without waiting for it
+pub fn open_url(url: &str) -> io::Result<()> {
+    if cfg!(target_os = "windows") {
+        Command::new("explorer").arg(url).spawn()?;
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg(url).spawn()?;
+    } else {
+        Command::new("xdg-open").arg(url).spawn()?;
+    }
+    Ok(())
+}
+
+fn spawn(launcher: &Path) -> io::Result<()> {
+    if cfg!(target_os = "macos") {
+        // App bundles are directories, which only `open` knows how to start
+        Command::new("open").arg(launcher).spawn()?;
+    } else {
+        Command::new(launcher).spawn()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,181 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::installer::{self, LoaderVersion, MinecraftVersion};
+
+// A lockfile pins the versions an install resolved to, like Cargo.lock, so everyone
+// installing from it gets the same ones. The hashes of the launch jsons meta served
+// for them catch meta serving different ones later
+pub const DEFAULT_PATH: &str = "quilt-install.lock";
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Lockfile {
+    pub minecraft_version: MinecraftVersion,
+    pub loader_version: LoaderVersion,
+    /// The SHA-256 of the client launch json
+    pub profile_json_sha256: String,
+    /// The SHA-256 of the server launch json
+    pub server_json_sha256: String,
+}
+
+/// Locks the versions, hashing the launch jsons meta serves for them
+pub async fn resolve(
+    client: &Client,
+    meta_urls: &[String],
+    minecraft_version: MinecraftVersion,
+    loader_version: LoaderVersion,
+) -> Result<Lockfile> {
+    let profile_json = installer::download_profile_json(
+        client,
+        meta_urls,
+        &minecraft_version,
+        &loader_version,
+        None,
+    )
+    .await?;
+    let server_json =
+        installer::download_server_json(client, meta_urls, &minecraft_version, &loader_version)
+            .await?;
+    Ok(Lockfile {
+        minecraft_version,
+        loader_version,
+        profile_json_sha256: sha256(&profile_json),
+        server_json_sha256: sha256(&server_json),
+    })
+}
+
+/// Reads the lockfile at `path`. Its hashes are checked by the install, against the launch json
+/// it downloads
+pub fn read(path: &Path) -> Result<Lockfile> {
+    let json = fs::read_to_string(path).with_context(|| {
+        format!(
+            "Could not read {}, create it with --update-lock first",
+            path.display()
+        )
+    })?;
+    serde_json::from_str(&json)
+        .with_context(|| format!("{} is not a valid lockfile", path.display()))
+}
+
+/// The hash a launch json from meta must have for an install from a lockfile to go ahead
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockedJson {
+    pub sha256: String,
+    /// The lockfile the hash is from, to tell how to lock the new json instead
+    pub path: PathBuf,
+}
+
+impl LockedJson {
+    /// Fails if meta served a launch json other than the locked one for the versions
+    pub fn verify(
+        &self,
+        json: &str,
+        minecraft_version: &MinecraftVersion,
+        loader_version: &LoaderVersion,
+    ) -> Result<()> {
+        if sha256(json) == self.sha256 {
+            return Ok(());
+        }
+        let mut command = format!("-m {minecraft_version} -l {loader_version} --update-lock");
+        if self.path != Path::new(DEFAULT_PATH) {
+            command += &format!(" --lockfile {}", self.path.display());
+        }
+        bail!(
+            "Quilt meta now serves a different launch json for Quilt Loader {loader_version} and \
+            Minecraft {minecraft_version} than when {} was written. To accept it, run the install \
+            again with `{command}` instead of --locked",
+            self.path.display()
+        );
+    }
+}
+
+pub fn write(path: &Path, lockfile: &Lockfile) -> Result<()> {
+    fs::write(path, serde_json::to_string_pretty(lockfile)?)
+        .with_context(|| format!("Could not write {}", path.display()))
+}
+
+fn sha256(json: &str) -> String {
+    format!("{:x}", Sha256::digest(json.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use super::*;
+
+    fn locked(json: &str, path: &str) -> LockedJson {
+        LockedJson {
+            sha256: sha256(json),
+            path: path.into(),
+        }
+    }
+
+    fn versions() -> (MinecraftVersion, LoaderVersion) {
+        (
+            MinecraftVersion {
+                version: "1.20.4".into(),
+                stable: true,
+                recommended: false,
+            },
+            LoaderVersion {
+                separator: '.',
+                build: 0,
+                maven: "org.quiltmc:quilt-loader:0.23.1".into(),
+                version: Version::parse("0.23.1").unwrap(),
+            },
+        )
+    }
+
+    #[test]
+    fn locked_json_passes() {
+        let (minecraft, loader) = versions();
+        let locked = locked(r#"{"id":"a"}"#, DEFAULT_PATH);
+        assert!(locked.verify(r#"{"id":"a"}"#, &minecraft, &loader).is_ok());
+    }
+
+    #[test]
+    fn changed_json_tells_how_to_lock_it() {
+        let (minecraft, loader) = versions();
+        let error = locked(r#"{"id":"a"}"#, DEFAULT_PATH)
+            .verify(r#"{"id":"b"}"#, &minecraft, &loader)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("`-m 1.20.4 -l 0.23.1 --update-lock`"),
+            "{error}"
+        );
+
+        let error = locked(r#"{"id":"a"}"#, "pack.lock")
+            .verify(r#"{"id":"b"}"#, &minecraft, &loader)
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("`-m 1.20.4 -l 0.23.1 --update-lock --lockfile pack.lock`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn lockfile_is_read_back_as_written() {
+        let (minecraft_version, loader_version) = versions();
+        let lockfile = Lockfile {
+            minecraft_version,
+            loader_version,
+            profile_json_sha256: sha256("profile"),
+            server_json_sha256: sha256("server"),
+        };
+        let path = std::env::temp_dir().join(format!("quilt-install-{}.lock", std::process::id()));
+        write(&path, &lockfile).unwrap();
+        let read_back = read(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(read_back.unwrap(), lockfile);
+    }
+}
@@ -1,32 +1,67 @@
 #![windows_subsystem = "windows"]
 
+use std::time::Duration;
+
 use anyhow::Context;
-use clap::Parser;
 
+mod bundle;
+mod cache;
 mod cli;
+mod crash;
 mod gui;
+mod icon;
 mod installer;
+mod java;
+mod launcher;
+mod lockfile;
+mod settings;
+mod tui;
+mod volume;
 
 const ICON: &[u8] = include_bytes!("../quilt.png");
 
 fn main() -> anyhow::Result<()> {
-    let args = cli::Args::parse();
-    let client = reqwest::Client::builder()
-        .user_agent(concat!(
-            env!("CARGO_PKG_NAME"),
-            '/',
-            env!("CARGO_PKG_VERSION"),
-        ))
-        .build()
-        .unwrap();
+    let args = cli::Args::parse_with_sources();
+    let mut client = reqwest::Client::builder()
+        .user_agent(args.user_agent())
+        .redirect(installer::redirect_policy())
+        // Fail on unreachable addresses instead of hanging on them
+        .connect_timeout(Duration::from_secs(args.connect_timeout))
+        .local_address(args.local_address())
+        .pool_idle_timeout(Duration::from_secs(args.pool_idle_timeout))
+        .pool_max_idle_per_host(args.pool_max_idle_per_host.unwrap_or(usize::MAX));
+    if let Some(timeout) = args.timeout {
+        client = client.timeout(Duration::from_secs(timeout));
+    }
+    let client = client.build().unwrap();
+    if let Some(max_rate) = args.max_rate {
+        installer::set_max_download_rate(max_rate);
+    }
+    installer::set_retries(args.retries);
+    if args.trace_requests {
+        installer::enable_request_tracing();
+    }
 
-    if args.subcommand.is_some() {
+    if args.tui {
         tokio::runtime::Runtime::new()
             .unwrap()
-            .block_on(cli::cli(client, args))
+            .block_on(tui::tui(client, args.meta_urls()))
             .context("Installation failed!")
+    } else if args.subcommand.is_some() {
+        let self_hash = args.self_hash;
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(cli::cli(client, args))
+            .context("Installation failed!")?;
+        if self_hash {
+            cli::print_self_hash()?;
+        }
+        Ok(())
+    } else if args.self_hash {
+        cli::print_self_hash()
     } else {
         println!("quilt-installer can also be used as a CLI! Run with --help for more information");
-        gui::run(client)
+        crash::install_hook();
+        gui::run(client, args.scale, args.meta_urls())
     }
 }
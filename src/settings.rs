@@ -0,0 +1,96 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// User preferences, read from `settings.json` in the config directory
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ThemeChoice,
+    /// Replaces the theme's accent color, as a hex code like `#7b4fbf`
+    pub accent_color: Option<String>,
+    /// Quilt meta mirrors to try in order, used unless `--meta-url` is passed
+    pub meta_urls: Vec<String>,
+    /// The launcher directory installed to last when several were detected,
+    /// which is preferred over the others while it still holds launcher profiles
+    pub client_directory: Option<PathBuf>,
+    /// Where the GUI window was and how large when it was last closed
+    pub window: Option<WindowGeometry>,
+    /// Only list the most recent versions in the GUI's version pickers, which are slow
+    /// to scroll through when they list every version
+    pub recent_versions_only: bool,
+    /// How many versions the pickers list when `recent_versions_only` is set
+    pub recent_version_count: Option<usize>,
+}
+
+/// The position and size of the GUI window, in the units the windowing system reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeChoice {
+    /// Follows the system's light or dark mode
+    #[default]
+    System,
+    Light,
+    Dark,
+    HighContrast,
+}
+
+#[cfg(target_os = "windows")]
+pub fn get_config_directory() -> PathBuf {
+    PathBuf::from(std::env::var("APPDATA").unwrap()).join("quilt-installer")
+}
+
+#[cfg(target_os = "macos")]
+pub fn get_config_directory() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap())
+        .join("Library")
+        .join("Application Support")
+        .join("quilt-installer")
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_config_directory() -> PathBuf {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(std::env::var("HOME").unwrap()).join(".config"))
+        .join("quilt-installer")
+}
+
+pub fn get_settings_path() -> PathBuf {
+    get_config_directory().join("settings.json")
+}
+
+/// Reads the settings file, falling back to the defaults when there is none or it is invalid
+pub fn load() -> Settings {
+    let path = get_settings_path();
+    let Ok(json) = fs::read_to_string(&path) else {
+        return Settings::default();
+    };
+    serde_json::from_str(&json).unwrap_or_else(|error| {
+        eprintln!("Ignoring invalid settings file {}: {error}", path.display());
+        Settings::default()
+    })
+}
+
+/// Changes one setting in the settings file, leaving the others as the user wrote them
+pub fn update(key: &str, value: impl Serialize) -> Result<()> {
+    let path = get_settings_path();
+    let mut settings: Map<String, Value> = match fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json)?,
+        Err(_) => Map::new(),
+    };
+    settings.insert(key.into(), serde_json::to_value(value)?);
+    fs::create_dir_all(get_config_directory())?;
+    fs::write(path, serde_json::to_string_pretty(&settings)?)?;
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+
+use anyhow::{bail, Result};
+use dialoguer::{Completion, Confirm, Input, Select};
+use reqwest::Client;
+
+use crate::cli::print_progress;
+use crate::installer::{
+    self, ClientInstallation, Installation, JsonStyle, OnConflict, ServerInstallation,
+    MOJANG_VERSION_MANIFEST_URL,
+};
+use crate::java::find_java_installations;
+
+pub async fn tui(client: Client, meta_urls: Vec<String>) -> Result<()> {
+    let minecraft_versions =
+        installer::fetch_minecraft_versions(client.clone(), &meta_urls).await?;
+    let loader_versions = installer::fetch_loader_versions(client.clone(), &meta_urls).await?;
+
+    let installation_type = match Select::new()
+        .with_prompt("Installation")
+        .items(&["Client", "Server"][..])
+        .default(0)
+        .interact()?
+    {
+        0 => Installation::Client,
+        _ => Installation::Server,
+    };
+
+    let show_snapshots = Confirm::new()
+        .with_prompt("Show snapshots?")
+        .default(false)
+        .interact()?;
+    let minecraft_versions: Vec<_> = minecraft_versions
+        .into_iter()
+        .filter(|v| show_snapshots || v.stable)
+        .collect();
+    if minecraft_versions.is_empty() {
+        bail!("Quilt meta lists no Minecraft versions to pick from");
+    }
+    let minecraft_version = minecraft_versions[Select::new()
+        .with_prompt("Minecraft version")
+        .items(&minecraft_versions[..])
+        .default(0)
+        .max_length(10)
+        .interact()?]
+    .clone();
+
+    let show_betas = Confirm::new()
+        .with_prompt("Show betas?")
+        .default(false)
+        .interact()?;
+    let loader_versions: Vec<_> = loader_versions
+        .into_iter()
+        .filter(|v| show_betas || v.version.pre.is_empty())
+        .collect();
+    if loader_versions.is_empty() {
+        bail!("Quilt meta lists no Quilt Loader versions to pick from");
+    }
+    let loader_version = loader_versions[Select::new()
+        .with_prompt("Loader version")
+        .items(&loader_versions[..])
+        .default(0)
+        .max_length(10)
+        .interact()?]
+    .clone();
+
+    match installation_type {
+        Installation::Client => {
+            let install_dir: String = Input::new()
+                .with_prompt("Directory")
+                .completion_with(&PathCompletion)
+                .default(installer::detect_client_directory().display().to_string())
+                .interact_text()?;
+            let generate_profile = Confirm::new()
+                .with_prompt("Generate profile?")
+                .default(true)
+                .interact()?;
+
+            installer::install_client(
+                client,
+                ClientInstallation {
+                    minecraft_version,
+                    loader_version,
+                    install_dir: install_dir.into(),
+                    versions_dir: None,
+                    profiles_file: None,
+                    id_suffix: None,
+                    keep_unknown_files: false,
+                    placeholder_jar: false,
+                    game_dir: None,
+                    allow_unsupported: false,
+                    exclude_libraries: Vec::new(),
+                    maven_loader: None,
+                    on_conflict: OnConflict::Overwrite,
+                    generate_profile,
+                    profile_type: Default::default(),
+                    offline: false,
+                    canonical: false,
+                    deterministic: false,
+                    json_style: JsonStyle::AsReceived,
+                    bundle: None,
+                    verbose: false,
+                    brief: false,
+                    meta_urls,
+                    mojang_meta_url: MOJANG_VERSION_MANIFEST_URL.into(),
+                    locked_json: None,
+                },
+                Some(&print_progress),
+            )
+            .await
+        }
+        Installation::Server => {
+            let install_dir: String = Input::new()
+                .with_prompt("Directory")
+                .completion_with(&PathCompletion)
+                .default(
+                    std::env::current_dir()
+                        .unwrap_or_default()
+                        .display()
+                        .to_string(),
+                )
+                .interact_text()?;
+            let download_jar = Confirm::new()
+                .with_prompt("Download server jar?")
+                .default(true)
+                .interact()?;
+            let generate_script = Confirm::new()
+                .with_prompt("Generate launch script?")
+                .default(true)
+                .interact()?;
+
+            let java_installations = find_java_installations();
+            let java_path: Option<PathBuf> = if generate_script && !java_installations.is_empty() {
+                let mut items: Vec<String> =
+                    java_installations.iter().map(ToString::to_string).collect();
+                items.push("java (from PATH)".into());
+                let selected = Select::new()
+                    .with_prompt("Java")
+                    .items(&items[..])
+                    .default(0)
+                    .interact()?;
+                java_installations.get(selected).map(|j| j.path.clone())
+            } else {
+                None
+            };
+
+            installer::install_server(
+                client,
+                ServerInstallation {
+                    minecraft_version,
+                    loader_version,
+                    install_dir: install_dir.into(),
+                    output_dir: None,
+                    download_jar,
+                    generate_script,
+                    all_scripts: false,
+                    force_download: false,
+                    shared_cache: false,
+                    java_path,
+                    launch_jar_name: None,
+                    mojang_meta_url: MOJANG_VERSION_MANIFEST_URL.into(),
+                    systemd_service: false,
+                    systemd_user: None,
+                    bundle: None,
+                    meta_urls,
+                    write_readme: None,
+                    brief: false,
+                    locked_json: None,
+                },
+                Some(&print_progress),
+            )
+            .await
+        }
+    }
+}
+
+/// Tab-completes the last component of a path to the directories it could name
+struct PathCompletion;
+
+impl Completion for PathCompletion {
+    fn get(&self, input: &str) -> Option<String> {
+        let (parent, prefix) = match input.rfind(['/', MAIN_SEPARATOR]) {
+            Some(i) => input.split_at(i + 1),
+            None => ("", input),
+        };
+        let dir = if parent.is_empty() {
+            Path::new(".")
+        } else {
+            Path::new(parent)
+        };
+
+        let mut names: Vec<String> = dir
+            .read_dir()
+            .ok()?
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let name = entry.file_name().into_string().ok()?;
+                (name.starts_with(prefix) && entry.path().is_dir()).then_some(name)
+            })
+            .collect();
+        names.sort();
+
+        // Complete the whole directory if there's only one, otherwise as far as all of them agree
+        let (first, last) = (names.first()?, names.last()?);
+        if names.len() == 1 {
+            return Some(format!("{parent}{first}{MAIN_SEPARATOR}"));
+        }
+        let common: usize = first
+            .chars()
+            .zip(last.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        Some(format!("{parent}{}", &first[..common]))
+    }
+}
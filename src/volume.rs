@@ -0,0 +1,131 @@
+//! Best-effort detection of install directories on network shares and removable drives,
+//! where a dropped connection or an unplugged drive can leave an install half-written
+
+use std::path::Path;
+
+/// A warning to show before installing to `dir`, if it looks like it's on a network share
+/// or a removable drive. Nothing is detected on platforms or setups this doesn't know about
+pub fn install_dir_warning(dir: &Path) -> Option<String> {
+    // The directory may not exist yet, so look at the closest one that does
+    let existing = dir.ancestors().find(|dir| dir.exists())?;
+    let dir = existing.canonicalize().ok()?;
+    let kind = detect_volume_kind(&dir)?;
+    Some(format!(
+        "{} is on a {kind}. Installing may be slow, and fail halfway if it disconnects",
+        dir.display()
+    ))
+}
+
+#[cfg(target_os = "windows")]
+fn detect_volume_kind(dir: &Path) -> Option<&'static str> {
+    use std::path::{Component, Prefix};
+
+    // Mapped network drives look like local ones, only UNC paths are told apart
+    match dir.components().next()? {
+        Component::Prefix(prefix) => match prefix.kind() {
+            Prefix::UNC(..) | Prefix::VerbatimUNC(..) => Some("network share"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn detect_volume_kind(dir: &Path) -> Option<&'static str> {
+    // The startup disk is mounted at `/`, other disks and network shares under `/Volumes`
+    dir.starts_with("/Volumes")
+        .then_some("network share or removable drive")
+}
+
+#[cfg(target_os = "linux")]
+fn detect_volume_kind(dir: &Path) -> Option<&'static str> {
+    const NETWORK_FILESYSTEMS: &[&str] = &[
+        "nfs",
+        "nfs4",
+        "cifs",
+        "smb3",
+        "smbfs",
+        "fuse.sshfs",
+        "fuse.rclone",
+        "9p",
+        "afs",
+    ];
+
+    // Each line is `<device> <mount point> <type> <options> ...`, the mount point escaped
+    let mounts = std::fs::read_to_string("/proc/self/mounts").ok()?;
+    let (device, _, fs_type) = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let device = fields.next()?;
+            let mount_point = unescape_mount_point(fields.next()?);
+            let fs_type = fields.next()?;
+            Some((device, mount_point, fs_type))
+        })
+        .filter(|(_, mount_point, _)| dir.starts_with(mount_point))
+        // The innermost mount is the one the directory is on
+        .max_by_key(|(_, mount_point, _)| mount_point.len())?;
+
+    if NETWORK_FILESYSTEMS.contains(&fs_type) {
+        return Some("network share");
+    }
+    is_removable_device(device).then_some("removable drive")
+}
+
+/// Decodes the `\NNN` octal escapes the kernel writes for spaces, tabs, newlines and backslashes
+/// in mount points
+#[cfg(target_os = "linux")]
+fn unescape_mount_point(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let digits = bytes.get(i + 1..i + 4).unwrap_or_default();
+        let escaped = (bytes[i] == b'\\' && digits.iter().all(u8::is_ascii_digit))
+            .then(|| std::str::from_utf8(digits).ok())
+            .flatten()
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match escaped {
+            Some(byte) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            None => {
+                unescaped.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Whether the kernel flags the disk a partition like `/dev/sdb1` belongs to as removable
+#[cfg(target_os = "linux")]
+fn is_removable_device(device: &str) -> bool {
+    let Some(name) = device.strip_prefix("/dev/") else {
+        return false;
+    };
+    // `/sys/class/block/<partition>` links into the disk's directory
+    let Ok(partition) = std::fs::canonicalize(Path::new("/sys/class/block").join(name)) else {
+        return false;
+    };
+    [partition.join("removable"), partition.join("../removable")]
+        .iter()
+        .any(|file| std::fs::read_to_string(file).map_or(false, |value| value.trim() == "1"))
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mount_points_are_unescaped() {
+        assert_eq!(unescape_mount_point("/mnt/my\\040share"), "/mnt/my share");
+        assert_eq!(unescape_mount_point("/mnt/a\\011b\\012c"), "/mnt/a\tb\nc");
+        assert_eq!(
+            unescape_mount_point("/mnt/back\\134slash"),
+            "/mnt/back\\slash"
+        );
+        assert_eq!(unescape_mount_point("/mnt/plain\\9"), "/mnt/plain\\9");
+    }
+}